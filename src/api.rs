@@ -0,0 +1,617 @@
+use std::collections::BTreeMap;
+use std::io::{self, BufRead, BufReader, ErrorKind, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
+
+use rayon::ThreadPoolBuilder;
+use serde::{Deserialize, Serialize};
+
+use crate::finder::{Finder, Match, SearchConfig};
+use crate::hasher::Algorithm;
+use crate::predicate::{Position, Predicate};
+use crate::stop::{StopToken, POLL_INTERVAL};
+
+/// Largest accepted body of a request.
+const MAX_BODY: usize = 64 * 1024;
+
+/// Quantity of the finished jobs kept for the status requests,
+/// the oldest of them are removed by the new jobs.
+const MAX_FINISHED_JOBS: usize = 100;
+
+/// Parameters of a job in the body of POST /jobs.
+/// The predicate is one of nulls, bits, prefix/suffix or target.
+/// The statuses return the parameters in the same form.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct JobRequest {
+    #[serde(default)]
+    algorithm: Algorithm,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    nulls: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bits: Option<usize>,
+    #[serde(default)]
+    position: Position,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    prefix: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    suffix: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    target: Option<String>,
+    hashes: usize,
+    #[serde(default)]
+    ordered: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    start: Option<u128>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    end: Option<u128>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    step: Option<usize>,
+}
+
+impl JobRequest {
+    fn config(&self) -> Result<SearchConfig, String> {
+        let modes = [
+            self.nulls.is_some(),
+            self.bits.is_some(),
+            self.prefix.is_some() || self.suffix.is_some(),
+            self.target.is_some(),
+        ];
+        if modes.iter().filter(|&&m| m).count() != 1 {
+            return Err("exactly one of nulls, bits, prefix/suffix or target is required".into());
+        }
+
+        let predicate = if let Some(nulls) = self.nulls {
+            Predicate::zeros(nulls, self.position)
+        } else if let Some(bits) = self.bits {
+            Predicate::bits(bits, self.position)
+        } else if let Some(target) = &self.target {
+            Predicate::target(target)?
+        } else {
+            Predicate::pattern(
+                self.prefix.as_deref().unwrap_or_default(),
+                self.suffix.as_deref().unwrap_or_default(),
+            )?
+        };
+
+        if self.step == Some(0) {
+            return Err("step should be positive".into());
+        }
+
+        Ok(SearchConfig::new(0, self.hashes)
+            .predicate(predicate)
+            .algorithm(self.algorithm)
+            .ordered(self.ordered)
+            .start(self.start.unwrap_or(1))
+            .end(self.end)
+            .step(self.step.unwrap_or(1)))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum State {
+    Running,
+    Done,
+    Cancelled,
+}
+
+/// Search submitted over the API.
+struct Job {
+    id: u64,
+    request: JobRequest,
+    finder: Finder,
+    stop: StopToken,
+    state: Mutex<JobState>,
+    /// notified on every found hash and at the end of the job
+    changed: Condvar,
+}
+
+struct JobState {
+    state: State,
+    matches: Vec<Match>,
+}
+
+/// Status of a job in the responses, serialized directly since
+/// the JSON values don't hold the 128-bit numbers.
+#[derive(Serialize)]
+struct JobStatus {
+    id: u64,
+    state: State,
+    config: JobRequest,
+    tried: u64,
+    found: usize,
+    /// null once all numbers are processed
+    next: Option<u128>,
+    matches: Vec<Match>,
+}
+
+impl Job {
+    /// Runs the search in its own pool of as many threads as the global
+    /// pool, so a long job doesn't hold the threads of the other jobs.
+    /// The global pool is shared if the threads can't be spawned.
+    fn run(&self) {
+        let finder = match ThreadPoolBuilder::new()
+            .num_threads(rayon::current_num_threads())
+            .build()
+        {
+            Ok(pool) => self.finder.clone().thread_pool(Arc::new(pool)),
+            Err(_) => self.finder.clone(),
+        };
+
+        finder.run_until(&self.stop, |m| {
+            self.state.lock().unwrap().matches.push(m);
+            self.changed.notify_all();
+        });
+
+        let mut state = self.state.lock().unwrap();
+        state.state = if self.stop.is_stopped() {
+            State::Cancelled
+        } else {
+            State::Done
+        };
+        self.changed.notify_all();
+    }
+
+    fn is_running(&self) -> bool {
+        self.state.lock().unwrap().state == State::Running
+    }
+
+    /// Stops the search and waits until its workers are over.
+    fn cancel(&self) {
+        self.stop.stop();
+
+        let mut state = self.state.lock().unwrap();
+        while state.state == State::Running {
+            state = self.changed.wait(state).unwrap();
+        }
+    }
+
+    fn status(&self) -> JobStatus {
+        let state = self.state.lock().unwrap();
+        let progress = self.finder.progress();
+
+        JobStatus {
+            id: self.id,
+            state: state.state,
+            config: self.request.clone(),
+            tried: progress.tried(),
+            found: state.matches.len(),
+            next: progress.next(),
+            matches: state.matches.clone(),
+        }
+    }
+}
+
+/// Local HTTP/JSON service which runs the submitted searches.
+///
+/// Endpoints:
+/// POST /jobs - submits a job, the body is a JSON object with the fields
+/// algorithm, nulls, bits, position, prefix, suffix, target, hashes,
+/// ordered, start, end, step;
+/// GET /jobs - statuses of all jobs;
+/// GET /jobs/{id} - status of the job with its found hashes;
+/// GET /jobs/{id}/matches - streams the found hashes as JSON lines
+/// until the job is over;
+/// DELETE /jobs/{id} - cancels the running job and waits for the stop
+/// of its workers, removes the finished one.
+/// Only the last MAX_FINISHED_JOBS finished jobs are kept.
+pub struct ApiServer {
+    listener: TcpListener,
+    jobs: Mutex<BTreeMap<u64, Arc<Job>>>,
+    /// ids aren't reused after the removal of the jobs
+    next_id: AtomicU64,
+}
+
+impl ApiServer {
+    pub fn bind<A: ToSocketAddrs>(addr: A) -> io::Result<Self> {
+        Ok(Self {
+            listener: TcpListener::bind(addr)?,
+            jobs: Mutex::new(BTreeMap::new()),
+            next_id: AtomicU64::new(1),
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Serves the requests until the stop token is triggered,
+    /// then cancels the running jobs.
+    pub fn run_until(&self, stop: &StopToken) -> io::Result<()> {
+        self.listener.set_nonblocking(true)?;
+
+        std::thread::scope(|scope| {
+            let mut result = Ok(());
+
+            while !stop.is_stopped() {
+                match self.listener.accept() {
+                    Ok((stream, _)) => {
+                        scope.spawn(move || {
+                            if let Some(job) = self.serve(stream, stop) {
+                                job.run();
+                            }
+                        });
+                    }
+                    Err(e) if e.kind() == ErrorKind::WouldBlock => {
+                        std::thread::sleep(POLL_INTERVAL);
+                    }
+                    Err(e) => {
+                        result = Err(e);
+                        break;
+                    }
+                }
+            }
+
+            // The scope waits for the threads of the jobs.
+            let jobs: Vec<Arc<Job>> = self.jobs.lock().unwrap().values().cloned().collect();
+            for job in jobs {
+                job.cancel();
+            }
+            result
+        })
+    }
+
+    /// Answers the request, out: the submitted job to run
+    /// on the thread of the connection, even if the client is gone.
+    fn serve(&self, stream: TcpStream, stop: &StopToken) -> Option<Arc<Job>> {
+        let mut writer = stream.try_clone().ok()?;
+        stream.set_nonblocking(false).ok()?;
+        stream
+            .set_read_timeout(Some(Duration::from_secs(10)))
+            .ok()?;
+
+        let request = match read_request(&mut BufReader::new(stream)) {
+            Ok(request) => request,
+            Err(e) => {
+                _ = respond_error(&mut writer, 400, &e.to_string());
+                return None;
+            }
+        };
+
+        let path = request.path.split('?').next().unwrap_or_default();
+        let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+        let job = |id: &str| -> Option<Arc<Job>> {
+            let id = id.parse().ok()?;
+            self.jobs.lock().unwrap().get(&id).cloned()
+        };
+
+        // Errors of the responses mean the client is gone.
+        _ = match (request.method.as_str(), segments.as_slice()) {
+            ("POST", ["jobs"]) => match self.submit(&request.body) {
+                Ok(job) => {
+                    _ = respond(&mut writer, 201, &job.status());
+                    _ = writer.shutdown(std::net::Shutdown::Both);
+                    return Some(job);
+                }
+                Err(e) => respond_error(&mut writer, 400, &e),
+            },
+            ("GET", ["jobs"]) => {
+                let jobs: Vec<Arc<Job>> = self.jobs.lock().unwrap().values().cloned().collect();
+                let statuses: Vec<_> = jobs.iter().map(|job| job.status()).collect();
+                respond(&mut writer, 200, &statuses)
+            }
+            ("GET", ["jobs", id]) => match job(id) {
+                Some(job) => respond(&mut writer, 200, &job.status()),
+                None => respond_error(&mut writer, 404, "job not found"),
+            },
+            ("GET", ["jobs", id, "matches"]) => match job(id) {
+                Some(job) => stream_matches(&mut writer, &job, stop),
+                None => respond_error(&mut writer, 404, "job not found"),
+            },
+            ("DELETE", ["jobs", id]) => match job(id) {
+                Some(job) if job.is_running() => {
+                    job.cancel();
+                    respond(&mut writer, 200, &job.status())
+                }
+                Some(job) => {
+                    self.jobs.lock().unwrap().remove(&job.id);
+                    respond(&mut writer, 200, &job.status())
+                }
+                None => respond_error(&mut writer, 404, "job not found"),
+            },
+            (_, ["jobs"]) | (_, ["jobs", _]) | (_, ["jobs", _, "matches"]) => {
+                respond_error(&mut writer, 405, "method not allowed")
+            }
+            _ => respond_error(&mut writer, 404, "not found"),
+        };
+
+        None
+    }
+
+    fn submit(&self, body: &[u8]) -> Result<Arc<Job>, String> {
+        let request: JobRequest = serde_json::from_slice(body).map_err(|e| e.to_string())?;
+        let config = request.config()?;
+
+        let mut jobs = self.jobs.lock().unwrap();
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+
+        let finished: Vec<u64> = jobs
+            .values()
+            .filter(|job| !job.is_running())
+            .map(|job| job.id)
+            .collect();
+        for id in finished.iter().rev().skip(MAX_FINISHED_JOBS - 1) {
+            jobs.remove(id);
+        }
+
+        let job = Arc::new(Job {
+            id,
+            request,
+            finder: Finder::new(config),
+            stop: StopToken::new(),
+            state: Mutex::new(JobState {
+                state: State::Running,
+                matches: Vec::new(),
+            }),
+            changed: Condvar::new(),
+        });
+        jobs.insert(id, job.clone());
+
+        Ok(job)
+    }
+}
+
+struct Request {
+    method: String,
+    path: String,
+    body: Vec<u8>,
+}
+
+fn read_request<R: BufRead>(reader: &mut R) -> io::Result<Request> {
+    let invalid = |message: &str| io::Error::new(ErrorKind::InvalidData, message.to_string());
+
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+
+    let mut parts = line.split_whitespace();
+    let method = parts.next().ok_or_else(|| invalid("empty request"))?;
+    let path = parts
+        .next()
+        .ok_or_else(|| invalid("request without path"))?;
+    let (method, path) = (method.to_string(), path.to_string());
+
+    let mut length = 0;
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return Err(invalid("unexpected end of headers"));
+        }
+
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                length = value
+                    .trim()
+                    .parse()
+                    .map_err(|_| invalid("invalid content length"))?;
+            }
+        }
+    }
+
+    if length > MAX_BODY {
+        return Err(invalid("request body is too large"));
+    }
+
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+
+    Ok(Request { method, path, body })
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        201 => "Created",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        _ => "",
+    }
+}
+
+fn respond<T: Serialize>(writer: &mut TcpStream, status: u16, body: &T) -> io::Result<()> {
+    let body = serde_json::to_vec(body)?;
+
+    write!(
+        writer,
+        "HTTP/1.1 {status} {}\r\nContent-Type: application/json\r\n\
+         Content-Length: {}\r\nConnection: close\r\n\r\n",
+        reason(status),
+        body.len()
+    )?;
+    writer.write_all(&body)?;
+    writer.flush()
+}
+
+fn respond_error(writer: &mut TcpStream, status: u16, message: &str) -> io::Result<()> {
+    respond(writer, status, &serde_json::json!({ "error": message }))
+}
+
+/// Writes the found hashes of the job as JSON lines as soon as they
+/// are found, the response ends with the job or the server.
+fn stream_matches(writer: &mut TcpStream, job: &Job, stop: &StopToken) -> io::Result<()> {
+    write!(
+        writer,
+        "HTTP/1.1 200 OK\r\nContent-Type: application/x-ndjson\r\nConnection: close\r\n\r\n"
+    )?;
+    writer.flush()?;
+
+    let mut sent = 0;
+
+    loop {
+        // The new matches are copied, so a slow client doesn't hold
+        // the lock of the job while they are written.
+        let (pending, over) = {
+            let mut state = job.state.lock().unwrap();
+            if sent == state.matches.len() && state.state == State::Running {
+                state = job.changed.wait_timeout(state, POLL_INTERVAL).unwrap().0;
+            }
+            (
+                state.matches[sent..].to_vec(),
+                state.state != State::Running,
+            )
+        };
+
+        for m in &pending {
+            let mut line = serde_json::to_vec(m)?;
+            line.push(b'\n');
+            writer.write_all(&line)?;
+        }
+        sent += pending.len();
+        writer.flush()?;
+
+        if over || stop.is_stopped() {
+            return Ok(());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    /// Sends the request, out: status code and body of the response.
+    fn request(addr: SocketAddr, method: &str, path: &str, body: &str) -> (u16, String) {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(
+            stream,
+            "{method} {path} HTTP/1.1\r\nHost: localhost\r\nContent-Length: {}\r\n\r\n{body}",
+            body.len()
+        )
+        .unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        let status = head.split_whitespace().nth(1).unwrap().parse().unwrap();
+
+        (status, body.to_string())
+    }
+
+    fn json(body: &str) -> serde_json::Value {
+        serde_json::from_str(body).unwrap()
+    }
+
+    #[test]
+    fn test_job_request_config() {
+        let request: JobRequest =
+            serde_json::from_str(r#"{"nulls": 3, "hashes": 2, "ordered": true}"#).unwrap();
+        assert_eq!(
+            request.config().unwrap(),
+            SearchConfig::new(3, 2).ordered(true)
+        );
+
+        let request: JobRequest =
+            serde_json::from_str(r#"{"bits": 3, "prefix": "00", "hashes": 2}"#).unwrap();
+        assert!(request.config().is_err());
+
+        assert!(serde_json::from_str::<JobRequest>(r#"{"nulls": 3, "f": 2}"#).is_err());
+    }
+
+    #[test]
+    fn test_read_request() {
+        let mut data: &[u8] = b"POST /jobs HTTP/1.1\r\ncontent-length: 2\r\n\r\n{}";
+        let request = read_request(&mut data).unwrap();
+
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.path, "/jobs");
+        assert_eq!(request.body, b"{}");
+    }
+
+    #[test]
+    fn test_api_jobs() {
+        let server = ApiServer::bind("127.0.0.1:0").unwrap();
+        let addr = server.local_addr().unwrap();
+        let stop = StopToken::new();
+
+        std::thread::scope(|scope| {
+            scope.spawn(|| server.run_until(&stop).unwrap());
+
+            let (status, body) = request(
+                addr,
+                "POST",
+                "/jobs",
+                r#"{"nulls": 3, "hashes": 3, "ordered": true}"#,
+            );
+            assert_eq!(status, 201);
+            let id = json(&body)["id"].as_u64().unwrap();
+
+            // The stream ends with the job.
+            let (status, body) = request(addr, "GET", &format!("/jobs/{id}/matches"), "");
+            assert_eq!(status, 200);
+            let numbers: Vec<u64> = body
+                .lines()
+                .map(|line| json(line)["number"].as_u64().unwrap())
+                .collect();
+            assert_eq!(numbers, vec![4163, 11848, 12843]);
+
+            let (status, body) = request(addr, "GET", &format!("/jobs/{id}"), "");
+            assert_eq!(status, 200);
+            assert_eq!(json(&body)["state"], "done");
+            assert_eq!(json(&body)["found"], 3);
+            assert_eq!(json(&body)["config"]["nulls"], 3);
+
+            // The job with the unreachable quantity of hashes is cancelled.
+            let (_, body) = request(addr, "POST", "/jobs", r#"{"nulls": 60, "hashes": 1}"#);
+            let id = json(&body)["id"].as_u64().unwrap();
+
+            let (status, body) = request(addr, "DELETE", &format!("/jobs/{id}"), "");
+            assert_eq!(status, 200);
+            assert_eq!(json(&body)["state"], "cancelled");
+
+            let (status, body) = request(addr, "GET", "/jobs", "");
+            assert_eq!(status, 200);
+            assert_eq!(json(&body).as_array().unwrap().len(), 2);
+
+            // The finished job is removed, the ids aren't reused.
+            assert_eq!(request(addr, "DELETE", &format!("/jobs/{id}"), "").0, 200);
+            assert_eq!(request(addr, "GET", &format!("/jobs/{id}"), "").0, 404);
+
+            let (_, body) = request(addr, "POST", "/jobs", r#"{"nulls": 1, "hashes": 1}"#);
+            assert_eq!(json(&body)["id"].as_u64().unwrap(), id + 1);
+
+            assert_eq!(request(addr, "GET", "/jobs/9", "").0, 404);
+            assert_eq!(request(addr, "PUT", "/jobs", "").0, 405);
+            assert_eq!(request(addr, "POST", "/jobs", "{}").0, 400);
+
+            stop.stop();
+        });
+    }
+
+    #[test]
+    fn test_api_concurrent_jobs() {
+        let server = ApiServer::bind("127.0.0.1:0").unwrap();
+        let addr = server.local_addr().unwrap();
+        let stop = StopToken::new();
+
+        std::thread::scope(|scope| {
+            scope.spawn(|| server.run_until(&stop).unwrap());
+
+            // The first job never ends, the second one has its own threads.
+            let (_, body) = request(addr, "POST", "/jobs", r#"{"nulls": 60, "hashes": 1}"#);
+            let first = json(&body)["id"].as_u64().unwrap();
+
+            let (_, body) = request(addr, "POST", "/jobs", r#"{"suffix": "0", "hashes": 1}"#);
+            let second = json(&body)["id"].as_u64().unwrap();
+            assert_eq!(json(&body)["config"]["suffix"], "0");
+
+            let (_, body) = request(addr, "GET", &format!("/jobs/{second}/matches"), "");
+            assert_eq!(body.lines().count(), 1);
+
+            let (_, body) = request(addr, "GET", &format!("/jobs/{second}"), "");
+            assert_eq!(json(&body)["state"], "done");
+            let (_, body) = request(addr, "GET", &format!("/jobs/{first}"), "");
+            assert_eq!(json(&body)["state"], "running");
+
+            stop.stop();
+        });
+    }
+}
@@ -0,0 +1,561 @@
+use std::fmt;
+use std::str::FromStr;
+
+use sha2::Digest;
+
+/// Largest quantity of messages hashed by one call of a backend.
+pub const MAX_LANES: usize = 16;
+
+/// Largest total size of the header tail and a message hashed in the lanes,
+/// the padded data takes at most two blocks.
+const MAX_LANE_DATA: usize = 2 * 64 - 9;
+
+const IV: [u32; 8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+];
+
+const K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+/// Implementation of the batched SHA-256.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    /// one message at a time by the sha2 crate, which uses SHA-NI
+    /// when the CPU supports it
+    Scalar,
+    /// 4 messages per call in the SSE2 registers
+    Sse2,
+    /// 8 messages per call in the AVX2 registers
+    Avx2,
+    /// 16 messages per call in the AVX-512 registers
+    Avx512,
+}
+
+impl Backend {
+    pub const ALL: [Backend; 4] = [
+        Backend::Scalar,
+        Backend::Sse2,
+        Backend::Avx2,
+        Backend::Avx512,
+    ];
+
+    /// Fastest backend of the CPU. The SHA extensions hash one message
+    /// faster than four SSE2 lanes, so SSE2 is used only without them.
+    pub fn detect() -> Self {
+        #[cfg(target_arch = "x86_64")]
+        {
+            if Backend::Avx512.is_supported() {
+                return Backend::Avx512;
+            }
+            if Backend::Avx2.is_supported() {
+                return Backend::Avx2;
+            }
+            if !is_x86_feature_detected!("sha") {
+                return Backend::Sse2;
+            }
+        }
+        Backend::Scalar
+    }
+
+    pub fn is_supported(&self) -> bool {
+        match self {
+            Backend::Scalar => true,
+            #[cfg(target_arch = "x86_64")]
+            Backend::Sse2 => is_x86_feature_detected!("sse2"),
+            #[cfg(target_arch = "x86_64")]
+            Backend::Avx2 => is_x86_feature_detected!("avx2"),
+            #[cfg(target_arch = "x86_64")]
+            Backend::Avx512 => is_x86_feature_detected!("avx512f"),
+            #[cfg(not(target_arch = "x86_64"))]
+            _ => false,
+        }
+    }
+
+    /// Quantity of messages hashed by one call.
+    pub fn lanes(&self) -> usize {
+        match self {
+            Backend::Scalar => 1,
+            Backend::Sse2 => 4,
+            Backend::Avx2 => 8,
+            Backend::Avx512 => 16,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Backend::Scalar => "scalar",
+            Backend::Sse2 => "sse2",
+            Backend::Avx2 => "avx2",
+            Backend::Avx512 => "avx512",
+        }
+    }
+}
+
+impl fmt::Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Backend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Backend::ALL
+            .into_iter()
+            .find(|backend| backend.name() == s)
+            .ok_or_else(|| {
+                format!("unknown backend {s}, expected one of: scalar, sse2, avx2, avx512")
+            })
+    }
+}
+
+/// SHA-256 of a constant header followed by each of the messages.
+/// The SIMD backends hash several messages per call, one in every lane
+/// of the registers, the blocks of the header are compressed once.
+#[derive(Debug, Clone)]
+pub struct Sha256Batch {
+    backend: Backend,
+    /// state after the whole blocks of the header
+    state: [u32; 8],
+    /// rest of the header after the whole blocks
+    tail: Vec<u8>,
+    /// size of the header in bytes
+    length: usize,
+    /// state of the scalar backend after the header
+    scalar: sha2::Sha256,
+}
+
+impl Sha256Batch {
+    /// Batch with the fastest backend of the CPU.
+    pub fn new(header: &[u8]) -> Self {
+        Self::build(header, Backend::detect())
+    }
+
+    pub fn with_backend(header: &[u8], backend: Backend) -> Result<Self, String> {
+        if backend.is_supported() {
+            Ok(Self::build(header, backend))
+        } else {
+            Err(format!("backend {backend} isn't supported by the CPU"))
+        }
+    }
+
+    fn build(header: &[u8], backend: Backend) -> Self {
+        let mut state = [IV; 1].map(|iv| iv.map(|word| [word]));
+        let blocks = header.chunks_exact(64);
+        let tail = blocks.remainder().to_vec();
+
+        for block in blocks {
+            // SAFETY: the scalar lanes don't need CPU features.
+            unsafe { compress::<u32, 1>(&mut state[0], &block_words(&[block])) };
+        }
+
+        Self {
+            backend,
+            state: state[0].map(|[word]| word),
+            tail,
+            length: header.len(),
+            scalar: sha2::Sha256::new_with_prefix(header),
+        }
+    }
+
+    pub fn backend(&self) -> Backend {
+        self.backend
+    }
+
+    /// Quantity of messages hashed by one call of the backend.
+    pub fn lanes(&self) -> usize {
+        self.backend.lanes()
+    }
+
+    /// Writes the digests of the header followed by each of the messages.
+    /// In: messages and digests of the same length, any quantity of them.
+    pub fn digest(&self, messages: &[&[u8]], digests: &mut [[u8; 32]]) {
+        assert_eq!(messages.len(), digests.len());
+
+        let lanes = self.lanes();
+        for (messages, digests) in messages.chunks(lanes).zip(digests.chunks_mut(lanes)) {
+            let fits = messages
+                .iter()
+                .all(|m| self.tail.len() + m.len() <= MAX_LANE_DATA);
+
+            match self.backend {
+                #[cfg(target_arch = "x86_64")]
+                Backend::Sse2 if fits => self.digest_lanes::<4>(messages, digests, compress_sse2),
+                #[cfg(target_arch = "x86_64")]
+                Backend::Avx2 if fits => self.digest_lanes::<8>(messages, digests, compress_avx2),
+                #[cfg(target_arch = "x86_64")]
+                Backend::Avx512 if fits => {
+                    self.digest_lanes::<16>(messages, digests, compress_avx512)
+                }
+                _ => self.digest_scalar(messages, digests),
+            }
+        }
+    }
+
+    fn digest_scalar(&self, messages: &[&[u8]], digests: &mut [[u8; 32]]) {
+        for (message, digest) in messages.iter().zip(digests) {
+            digest.copy_from_slice(&self.scalar.clone().chain_update(message).finalize());
+        }
+    }
+
+    /// Hashes up to N messages in the lanes.
+    /// In: compress - compression function of the backend, which is
+    /// supported by the CPU, the data of the lanes fits in two blocks.
+    #[cfg_attr(not(target_arch = "x86_64"), allow(dead_code))]
+    fn digest_lanes<const N: usize>(
+        &self,
+        messages: &[&[u8]],
+        digests: &mut [[u8; 32]],
+        compress: unsafe fn(&mut [[u32; N]; 8], &[[u32; N]; 16]),
+    ) {
+        // Padded data of the lanes. The unused lanes stay zeroed, they
+        // aren't valid messages, their digests are discarded.
+        let mut data = [[0u8; 128]; N];
+        let mut blocks = [1; N];
+
+        for (lane, message) in messages.iter().enumerate() {
+            let size = self.tail.len() + message.len();
+            let bits = ((self.length + message.len()) as u64) * 8;
+            blocks[lane] = (size + 9).div_ceil(64);
+
+            let data = &mut data[lane];
+            data[..self.tail.len()].copy_from_slice(&self.tail);
+            data[self.tail.len()..size].copy_from_slice(message);
+            data[size] = 0x80;
+            data[blocks[lane] * 64 - 8..blocks[lane] * 64].copy_from_slice(&bits.to_be_bytes());
+        }
+
+        let mut state = self.state.map(|word| [word; N]);
+        let last = blocks.iter().max().copied().unwrap_or(1);
+
+        for block in 0..last {
+            let lanes: [&[u8]; N] = std::array::from_fn(|lane| &data[lane][block * 64..][..64]);
+
+            // SAFETY: the caller checked the CPU features of the backend.
+            unsafe { compress(&mut state, &block_words(&lanes)) };
+
+            for (lane, digest) in digests.iter_mut().enumerate() {
+                if blocks[lane] == block + 1 {
+                    for (word, bytes) in state.iter().zip(digest.chunks_exact_mut(4)) {
+                        bytes.copy_from_slice(&word[lane].to_be_bytes());
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Big-endian words of the blocks of the lanes, word by word.
+fn block_words<const N: usize>(blocks: &[&[u8]; N]) -> [[u32; N]; 16] {
+    std::array::from_fn(|i| {
+        std::array::from_fn(|lane| {
+            let bytes = &blocks[lane][i * 4..i * 4 + 4];
+            u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+        })
+    })
+}
+
+/// Operations on the lanes of 32-bit words of a register.
+/// The functions are unsafe, the CPU should support the instructions.
+trait Lanes<const N: usize>: Copy {
+    unsafe fn load(words: &[u32; N]) -> Self;
+    unsafe fn store(self, words: &mut [u32; N]);
+    unsafe fn splat(word: u32) -> Self;
+    unsafe fn add(self, other: Self) -> Self;
+    unsafe fn xor(self, other: Self) -> Self;
+    unsafe fn and(self, other: Self) -> Self;
+    /// !self & other
+    unsafe fn andnot(self, other: Self) -> Self;
+    unsafe fn shr<const BITS: i32>(self) -> Self;
+    /// Rotation right, LEFT = 32 - RIGHT.
+    unsafe fn rotr<const RIGHT: i32, const LEFT: i32>(self) -> Self;
+}
+
+impl Lanes<1> for u32 {
+    #[inline(always)]
+    unsafe fn load(words: &[u32; 1]) -> Self {
+        words[0]
+    }
+    #[inline(always)]
+    unsafe fn store(self, words: &mut [u32; 1]) {
+        words[0] = self;
+    }
+    #[inline(always)]
+    unsafe fn splat(word: u32) -> Self {
+        word
+    }
+    #[inline(always)]
+    unsafe fn add(self, other: Self) -> Self {
+        self.wrapping_add(other)
+    }
+    #[inline(always)]
+    unsafe fn xor(self, other: Self) -> Self {
+        self ^ other
+    }
+    #[inline(always)]
+    unsafe fn and(self, other: Self) -> Self {
+        self & other
+    }
+    #[inline(always)]
+    unsafe fn andnot(self, other: Self) -> Self {
+        !self & other
+    }
+    #[inline(always)]
+    unsafe fn shr<const BITS: i32>(self) -> Self {
+        self >> BITS
+    }
+    #[inline(always)]
+    unsafe fn rotr<const RIGHT: i32, const LEFT: i32>(self) -> Self {
+        self.rotate_right(RIGHT as u32)
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+mod x86 {
+    use super::Lanes;
+    use std::arch::x86_64::*;
+
+    impl Lanes<4> for __m128i {
+        #[inline(always)]
+        unsafe fn load(words: &[u32; 4]) -> Self {
+            _mm_loadu_si128(words.as_ptr().cast())
+        }
+        #[inline(always)]
+        unsafe fn store(self, words: &mut [u32; 4]) {
+            _mm_storeu_si128(words.as_mut_ptr().cast(), self)
+        }
+        #[inline(always)]
+        unsafe fn splat(word: u32) -> Self {
+            _mm_set1_epi32(word as i32)
+        }
+        #[inline(always)]
+        unsafe fn add(self, other: Self) -> Self {
+            _mm_add_epi32(self, other)
+        }
+        #[inline(always)]
+        unsafe fn xor(self, other: Self) -> Self {
+            _mm_xor_si128(self, other)
+        }
+        #[inline(always)]
+        unsafe fn and(self, other: Self) -> Self {
+            _mm_and_si128(self, other)
+        }
+        #[inline(always)]
+        unsafe fn andnot(self, other: Self) -> Self {
+            _mm_andnot_si128(self, other)
+        }
+        #[inline(always)]
+        unsafe fn shr<const BITS: i32>(self) -> Self {
+            _mm_srli_epi32::<BITS>(self)
+        }
+        #[inline(always)]
+        unsafe fn rotr<const RIGHT: i32, const LEFT: i32>(self) -> Self {
+            _mm_or_si128(_mm_srli_epi32::<RIGHT>(self), _mm_slli_epi32::<LEFT>(self))
+        }
+    }
+
+    impl Lanes<8> for __m256i {
+        #[inline(always)]
+        unsafe fn load(words: &[u32; 8]) -> Self {
+            _mm256_loadu_si256(words.as_ptr().cast())
+        }
+        #[inline(always)]
+        unsafe fn store(self, words: &mut [u32; 8]) {
+            _mm256_storeu_si256(words.as_mut_ptr().cast(), self)
+        }
+        #[inline(always)]
+        unsafe fn splat(word: u32) -> Self {
+            _mm256_set1_epi32(word as i32)
+        }
+        #[inline(always)]
+        unsafe fn add(self, other: Self) -> Self {
+            _mm256_add_epi32(self, other)
+        }
+        #[inline(always)]
+        unsafe fn xor(self, other: Self) -> Self {
+            _mm256_xor_si256(self, other)
+        }
+        #[inline(always)]
+        unsafe fn and(self, other: Self) -> Self {
+            _mm256_and_si256(self, other)
+        }
+        #[inline(always)]
+        unsafe fn andnot(self, other: Self) -> Self {
+            _mm256_andnot_si256(self, other)
+        }
+        #[inline(always)]
+        unsafe fn shr<const BITS: i32>(self) -> Self {
+            _mm256_srli_epi32::<BITS>(self)
+        }
+        #[inline(always)]
+        unsafe fn rotr<const RIGHT: i32, const LEFT: i32>(self) -> Self {
+            _mm256_or_si256(
+                _mm256_srli_epi32::<RIGHT>(self),
+                _mm256_slli_epi32::<LEFT>(self),
+            )
+        }
+    }
+
+    impl Lanes<16> for __m512i {
+        #[inline(always)]
+        unsafe fn load(words: &[u32; 16]) -> Self {
+            _mm512_loadu_si512(words.as_ptr().cast())
+        }
+        #[inline(always)]
+        unsafe fn store(self, words: &mut [u32; 16]) {
+            _mm512_storeu_si512(words.as_mut_ptr().cast(), self)
+        }
+        #[inline(always)]
+        unsafe fn splat(word: u32) -> Self {
+            _mm512_set1_epi32(word as i32)
+        }
+        #[inline(always)]
+        unsafe fn add(self, other: Self) -> Self {
+            _mm512_add_epi32(self, other)
+        }
+        #[inline(always)]
+        unsafe fn xor(self, other: Self) -> Self {
+            _mm512_xor_si512(self, other)
+        }
+        #[inline(always)]
+        unsafe fn and(self, other: Self) -> Self {
+            _mm512_and_si512(self, other)
+        }
+        #[inline(always)]
+        unsafe fn andnot(self, other: Self) -> Self {
+            _mm512_andnot_si512(self, other)
+        }
+        #[inline(always)]
+        unsafe fn shr<const BITS: i32>(self) -> Self {
+            _mm512_srl_epi32(self, _mm_cvtsi32_si128(BITS))
+        }
+        #[inline(always)]
+        unsafe fn rotr<const RIGHT: i32, const LEFT: i32>(self) -> Self {
+            _mm512_ror_epi32::<RIGHT>(self)
+        }
+    }
+
+    #[target_feature(enable = "sse2")]
+    pub(super) unsafe fn compress_sse2(state: &mut [[u32; 4]; 8], block: &[[u32; 4]; 16]) {
+        super::compress::<__m128i, 4>(state, block)
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub(super) unsafe fn compress_avx2(state: &mut [[u32; 8]; 8], block: &[[u32; 8]; 16]) {
+        super::compress::<__m256i, 8>(state, block)
+    }
+
+    #[target_feature(enable = "avx512f")]
+    pub(super) unsafe fn compress_avx512(state: &mut [[u32; 16]; 8], block: &[[u32; 16]; 16]) {
+        super::compress::<__m512i, 16>(state, block)
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+use x86::{compress_avx2, compress_avx512, compress_sse2};
+
+/// SHA-256 compression of one block in every lane.
+#[inline(always)]
+unsafe fn compress<V: Lanes<N>, const N: usize>(state: &mut [[u32; N]; 8], block: &[[u32; N]; 16]) {
+    let mut w = [V::splat(0); 64];
+
+    for i in 0..16 {
+        w[i] = V::load(&block[i]);
+    }
+    for i in 16..64 {
+        let s0 = w[i - 15]
+            .rotr::<7, 25>()
+            .xor(w[i - 15].rotr::<18, 14>())
+            .xor(w[i - 15].shr::<3>());
+        let s1 = w[i - 2]
+            .rotr::<17, 15>()
+            .xor(w[i - 2].rotr::<19, 13>())
+            .xor(w[i - 2].shr::<10>());
+        w[i] = w[i - 16].add(s0).add(w[i - 7]).add(s1);
+    }
+
+    let initial: [V; 8] = std::array::from_fn(|i| V::load(&state[i]));
+    let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = initial;
+
+    for i in 0..64 {
+        let s1 = e
+            .rotr::<6, 26>()
+            .xor(e.rotr::<11, 21>())
+            .xor(e.rotr::<25, 7>());
+        let ch = e.and(f).xor(e.andnot(g));
+        let t1 = h.add(s1).add(ch).add(V::splat(K[i])).add(w[i]);
+        let s0 = a
+            .rotr::<2, 30>()
+            .xor(a.rotr::<13, 19>())
+            .xor(a.rotr::<22, 10>());
+        let maj = a.and(b).xor(a.and(c)).xor(b.and(c));
+        let t2 = s0.add(maj);
+
+        h = g;
+        g = f;
+        f = e;
+        e = d.add(t1);
+        d = c;
+        c = b;
+        b = a;
+        a = t1.add(t2);
+    }
+
+    for (i, v) in [a, b, c, d, e, f, g, h].into_iter().enumerate() {
+        initial[i].add(v).store(&mut state[i]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn messages() -> Vec<Vec<u8>> {
+        (0..37)
+            .map(|i: usize| (i * 7919 + i.pow(5)).to_string().into_bytes())
+            .chain([Vec::new(), vec![b'x'; 55], vec![b'y'; 56], vec![b'z'; 300]])
+            .collect()
+    }
+
+    #[test]
+    fn test_backends() {
+        let messages = messages();
+        let messages: Vec<&[u8]> = messages.iter().map(|m| m.as_slice()).collect();
+
+        for header_size in [0, 3, 55, 64, 100, 130] {
+            let header: Vec<u8> = (0..header_size).map(|i| i as u8).collect();
+
+            let expected: Vec<String> = messages
+                .iter()
+                .map(|m| sha256::digest([&header[..], m].concat().as_slice()))
+                .collect();
+
+            for backend in Backend::ALL.into_iter().filter(|b| b.is_supported()) {
+                let batch = Sha256Batch::with_backend(&header, backend).unwrap();
+                let mut digests = vec![[0; 32]; messages.len()];
+                batch.digest(&messages, &mut digests);
+
+                let digests: Vec<String> = digests.iter().map(hex::encode).collect();
+                assert_eq!(digests, expected, "{backend}, header {header_size}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_backend_from_str() {
+        for backend in Backend::ALL {
+            assert_eq!(backend.name().parse::<Backend>(), Ok(backend));
+        }
+        assert!(Backend::Scalar.is_supported());
+        assert!(Backend::detect().is_supported());
+    }
+}
@@ -0,0 +1,186 @@
+use std::hint::black_box;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+use crate::batch::Sha256Batch;
+use crate::encoding::MAX_ENCODED_LEN;
+use crate::finder::{Finder, SearchConfig};
+use crate::hasher::{Algorithm, MAX_OUTPUT_SIZE};
+use crate::stop::StopToken;
+
+/// Largest quantity of numbers of the measure of the stages,
+/// the representations and digests of them are kept in memory.
+const STAGE_NUMBERS: usize = 100_000;
+
+/// Cost of the stages of the processing of one number, in nanoseconds,
+/// measured in one thread.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Stages {
+    /// representation of the number in the format of the search
+    pub formatting: f64,
+    /// digest of the header, the representation and the trailer,
+    /// SHA-256 is hashed in batches like in the search
+    pub hashing: f64,
+    /// check of the digest by the predicate
+    pub checking: f64,
+}
+
+impl Stages {
+    pub fn total(&self) -> f64 {
+        self.formatting + self.hashing + self.checking
+    }
+}
+
+/// Throughput of the search with the quantity of threads
+/// and the chunk size.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Throughput {
+    pub threads: usize,
+    pub chunk_size: usize,
+    pub hashes_per_second: f64,
+}
+
+/// Results of the benchmark of an algorithm.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BenchResult {
+    pub algorithm: Algorithm,
+    pub stages: Stages,
+    pub runs: Vec<Throughput>,
+}
+
+/// Measures the stages of the processing of the numbers 1..=numbers
+/// in the format and the payload of the search.
+pub fn measure_stages(config: &SearchConfig, numbers: usize) -> Stages {
+    let numbers = numbers.max(1);
+    let per_number = |elapsed: Duration| elapsed.as_nanos() as f64 / numbers as f64;
+    let format = config.format;
+
+    let started = Instant::now();
+    let mut buffer = [0; MAX_ENCODED_LEN];
+    for number in 1..=numbers as u128 {
+        black_box(format.write(black_box(number), &mut buffer));
+    }
+    let formatting = per_number(started.elapsed());
+
+    let texts: Vec<Vec<u8>> = (1..=numbers as u128).map(|n| format.encode(n)).collect();
+    let mut digests = vec![[0; MAX_OUTPUT_SIZE]; numbers];
+    let mut size = 0;
+
+    let hashing = if config.is_batched() {
+        // The trailer is a part of the messages of the batches.
+        let batch = Sha256Batch::new(&config.header);
+        let messages: Vec<Vec<u8>> = texts
+            .iter()
+            .map(|text| [text, &config.trailer[..]].concat())
+            .collect();
+        let messages: Vec<&[u8]> = messages.iter().map(Vec::as_slice).collect();
+        let mut batch_digests = vec![[0; 32]; numbers];
+
+        let started = Instant::now();
+        batch.digest(black_box(&messages), &mut batch_digests);
+        let elapsed = started.elapsed();
+
+        for (digest, batch_digest) in digests.iter_mut().zip(&batch_digests) {
+            digest[..32].copy_from_slice(batch_digest);
+        }
+        size = 32;
+        per_number(elapsed)
+    } else {
+        let payload = config.payload();
+
+        let started = Instant::now();
+        for (text, digest) in texts.iter().zip(&mut digests) {
+            size = payload.digest_encoded_to(black_box(text), digest);
+        }
+        per_number(started.elapsed())
+    };
+
+    let started = Instant::now();
+    for digest in &digests {
+        black_box(config.predicate.matches(black_box(&digest[..size])));
+    }
+    let checking = per_number(started.elapsed());
+
+    Stages {
+        formatting,
+        hashing,
+        checking,
+    }
+}
+
+/// Measures the throughput of the search over the numbers 1..=numbers
+/// in a pool of the threads.
+pub fn measure_throughput(
+    config: &SearchConfig,
+    threads: usize,
+    numbers: usize,
+) -> Result<Throughput, String> {
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build()
+        .map_err(|e| e.to_string())?;
+
+    let finder = Finder::new(config.clone().start(1).end(Some(numbers.max(1) as u128)))
+        .thread_pool(Arc::new(pool));
+
+    let started = Instant::now();
+    finder.count_until(&StopToken::new(), |_| {});
+    let elapsed = started.elapsed();
+
+    Ok(Throughput {
+        threads,
+        chunk_size: config.chunk_size,
+        hashes_per_second: finder.progress().tried() as f64 / elapsed.as_secs_f64(),
+    })
+}
+
+/// Measures the algorithm of the config with every quantity
+/// of threads and every chunk size.
+pub fn bench(
+    config: &SearchConfig,
+    threads: &[usize],
+    chunk_sizes: &[usize],
+    numbers: usize,
+) -> Result<BenchResult, String> {
+    let mut runs = Vec::new();
+
+    for &threads in threads {
+        for &chunk_size in chunk_sizes {
+            let config = config.clone().chunk_size(chunk_size);
+            runs.push(measure_throughput(&config, threads, numbers)?);
+        }
+    }
+
+    Ok(BenchResult {
+        algorithm: config.algorithm,
+        stages: measure_stages(config, numbers.min(STAGE_NUMBERS)),
+        runs,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bench() {
+        let config = SearchConfig::new(3, 1).algorithm(Algorithm::Md5);
+        let result = bench(&config, &[1, 2], &[100, 1000], 5000).unwrap();
+
+        assert_eq!(result.algorithm, Algorithm::Md5);
+        assert_eq!(result.runs.len(), 4);
+        assert_eq!(
+            (result.runs[3].threads, result.runs[3].chunk_size),
+            (2, 1000)
+        );
+        assert!(result.runs.iter().all(|run| run.hashes_per_second > 0.0));
+        assert!(result.stages.hashing > 0.0);
+        assert!(result.stages.total() >= result.stages.hashing);
+
+        let json = serde_json::to_value(&result).unwrap();
+        assert_eq!(json["algorithm"], "md5");
+        assert_eq!(json["runs"][0]["threads"], 1);
+    }
+}
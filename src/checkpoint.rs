@@ -0,0 +1,223 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::encoding::NumberFormat;
+use crate::finder::{Match, SearchConfig};
+
+/// State of the search saved to resume it after an interruption.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    /// description of the search, only the same search can be resumed
+    pub search: String,
+    /// all numbers below it are processed, none if all numbers are
+    pub next: Option<u128>,
+    /// hashes reported so far, some of them may be above next
+    pub matches: Vec<Match>,
+}
+
+impl Checkpoint {
+    /// Checkpoint of the search which isn't started yet.
+    pub fn new(config: &SearchConfig) -> Self {
+        Self {
+            search: describe(config),
+            next: Some(config.resume_from.unwrap_or(config.start)),
+            matches: Vec::new(),
+        }
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let data = fs::read(path)?;
+        serde_json::from_slice(&data).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// Saves the checkpoint through a temporary file, so an interruption
+    /// never leaves a broken checkpoint behind.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut temp = path.as_os_str().to_owned();
+        temp.push(".tmp");
+
+        fs::write(&temp, serde_json::to_vec(self)?)?;
+        fs::rename(&temp, path)
+    }
+
+    /// Config which continues the search from the checkpoint.
+    /// The numbers below next are skipped, the hashes found below next
+    /// are subtracted from the quantity of hashes to find.
+    pub fn resume(&self, config: &SearchConfig) -> Result<SearchConfig, String> {
+        if self.search != describe(config) {
+            return Err(format!(
+                "checkpoint of another search: {}, expected: {}",
+                self.search,
+                describe(config)
+            ));
+        }
+
+        // The processed search continues from its last number,
+        // the hash of it is known if it is found.
+        let Some(next) = self.next.or(config.last_number()) else {
+            return Ok(config.clone());
+        };
+        let below = self.matches.iter().filter(|m| m.number < next).count();
+
+        Ok(config
+            .clone()
+            .resume_from(Some(next))
+            .hashes(config.hashes.saturating_sub(below)))
+    }
+
+    /// The hash of the number is already reported.
+    pub fn is_known(&self, number: u128) -> bool {
+        self.matches.iter().any(|m| m.number == number)
+    }
+
+    /// The hash of the number should be reported: it isn't known and
+    /// fewer than the quantity of hashes are reported so far.
+    pub fn accepts(&self, number: u128, hashes: usize) -> bool {
+        self.matches.len() < hashes && !self.is_known(number)
+    }
+
+    /// Moves the checkpoint forward to the frontier of the search.
+    pub fn advance(&mut self, next: Option<u128>) {
+        self.next = match (self.next, next) {
+            (Some(current), Some(next)) => Some(current.max(next)),
+            _ => None,
+        };
+    }
+}
+
+fn describe(config: &SearchConfig) -> String {
+    let end = config.end.map_or("none".to_string(), |end| end.to_string());
+
+    let mut search = format!(
+        "algorithm={} {} hashes={} ordered={} start={} end={} step={} shard={}",
+        config.algorithm,
+        config.predicate,
+        config.hashes,
+        config.ordered,
+        config.start,
+        end,
+        config.step,
+        config.shard
+    );
+
+    // Searches of the plain decimal numbers keep the earlier description.
+    if !config.header.is_empty() {
+        search.push_str(&format!(" header={}", hex::encode(&config.header)));
+    }
+    if config.format != NumberFormat::default() {
+        search.push_str(&format!(" format={}", config.format));
+    }
+    if !config.trailer.is_empty() {
+        search.push_str(&format!(" trailer={}", hex::encode(&config.trailer)));
+    }
+    search
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::finder::Finder;
+    use std::time::Duration;
+
+    fn found(number: u128) -> Match {
+        Match {
+            number,
+            hash: String::new(),
+            elapsed: Duration::ZERO,
+        }
+    }
+
+    #[test]
+    fn test_save_load() {
+        let path = std::env::temp_dir().join(format!("hash_finder_{}.json", std::process::id()));
+
+        let mut checkpoint = Checkpoint::new(&SearchConfig::new(3, 2));
+        checkpoint.advance(Some(5000));
+        checkpoint.matches.push(found(4163));
+
+        checkpoint.save(&path).unwrap();
+        let loaded = Checkpoint::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded, checkpoint);
+    }
+
+    #[test]
+    fn test_resume() {
+        let config = SearchConfig::new(3, 3);
+
+        let mut checkpoint = Checkpoint::new(&config);
+        checkpoint.advance(Some(5000));
+        checkpoint.advance(Some(10));
+        checkpoint.matches.push(found(4163));
+        checkpoint.matches.push(found(12843));
+
+        let resumed = checkpoint.resume(&config).unwrap();
+
+        assert_eq!(resumed.resume_from, Some(5000));
+        assert_eq!(resumed.hashes, 2);
+        assert!(checkpoint.is_known(12843));
+        assert!(!checkpoint.is_known(11848));
+
+        // The processed search is resumed from its last number.
+        let mut processed = checkpoint.clone();
+        processed.advance(None);
+        processed.advance(Some(6000));
+        assert_eq!(processed.next, None);
+
+        let resumed = processed.resume(&config).unwrap();
+        assert_eq!(resumed.resume_from, Some(u64::MAX as u128));
+        assert_eq!(resumed.hashes, 1);
+        assert!(checkpoint.resume(&SearchConfig::new(4, 3)).is_err());
+        assert!(checkpoint
+            .resume(&SearchConfig::new(3, 3).header(b"block".to_vec()))
+            .is_err());
+        assert!(checkpoint
+            .resume(&SearchConfig::new(3, 3).template("{n:x}".parse().unwrap()))
+            .is_err());
+    }
+
+    #[test]
+    fn test_resume_known_above_next() {
+        let config = SearchConfig::new(3, 3);
+
+        // The search was interrupted at 5000 after it reported
+        // the hashes above it, the quantity is already complete.
+        let mut checkpoint = Checkpoint::new(&config);
+        checkpoint.advance(Some(5000));
+        checkpoint.matches.push(found(4163));
+        checkpoint.matches.push(found(11848));
+        checkpoint.matches.push(found(12843));
+
+        let finder = Finder::new(checkpoint.resume(&config).unwrap());
+        let mut reported = Vec::new();
+
+        finder.run(|m| {
+            if checkpoint.accepts(m.number, config.hashes) {
+                reported.push(m.number);
+                checkpoint.matches.push(m);
+            }
+        });
+
+        assert!(reported.is_empty());
+
+        // One more hash to find, the known hashes aren't repeated.
+        let config = SearchConfig::new(3, 4);
+        checkpoint.search = describe(&config);
+
+        let finder = Finder::new(checkpoint.resume(&config).unwrap());
+        finder.run(|m| {
+            if checkpoint.accepts(m.number, config.hashes) {
+                reported.push(m.number);
+                checkpoint.matches.push(m);
+            }
+        });
+
+        assert_eq!(reported.len(), 1);
+        assert!(reported[0] > 12843);
+        assert_eq!(checkpoint.matches.len(), 4);
+    }
+}
@@ -0,0 +1,771 @@
+use std::collections::HashMap;
+use std::io::{self, BufRead, BufReader, ErrorKind, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::ops::Range;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, RecvTimeoutError, Sender};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::finder::{check_hash, Collector, Finder, Match, SearchConfig};
+use crate::payload::Payload;
+use crate::progress::Progress;
+use crate::scheduler::Sequence;
+use crate::stop::{StopToken, POLL_INTERVAL};
+
+/// Default quantity of numbers handed out to a remote worker at once.
+pub const DEFAULT_JOB_SIZE: usize = 1 << 20;
+
+/// Default time after which the range of a silent worker
+/// is handed out to another worker.
+pub const DEFAULT_LEASE: Duration = Duration::from_secs(30);
+
+/// Shortest lease, the workers send heartbeats every third of it.
+pub const MIN_LEASE: Duration = Duration::from_secs(1);
+
+/// Message of a worker to the coordinator, one JSON object per line.
+/// Every request gets a response.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", content = "data", rename_all = "snake_case")]
+enum Request {
+    Claim,
+    Found {
+        job: u64,
+        number: u128,
+        hash: String,
+    },
+    Heartbeat {
+        job: u64,
+    },
+    Complete {
+        job: u64,
+    },
+}
+
+/// Message of the coordinator to a worker, one JSON object per line.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", content = "data", rename_all = "snake_case")]
+#[allow(clippy::large_enum_variant)]
+enum Response {
+    /// Numbers of the search from..=to to process,
+    /// heartbeats are expected with the interval in milliseconds.
+    Job {
+        job: u64,
+        config: SearchConfig,
+        from: u128,
+        to: u128,
+        heartbeat: u64,
+    },
+    /// No range to hand out at the moment, claim again after
+    /// the interval in milliseconds.
+    Wait {
+        millis: u64,
+    },
+    Ack,
+    /// The job is handed out to another worker.
+    Cancel,
+    /// The search is over.
+    Stop,
+}
+
+/// Coordinator of the search which hands out ranges of numbers
+/// to the remote workers connected over TCP.
+pub struct Coordinator {
+    config: SearchConfig,
+    listener: TcpListener,
+    job_size: usize,
+    lease: Duration,
+    progress: Progress,
+}
+
+impl Coordinator {
+    pub fn bind<A: ToSocketAddrs>(addr: A, config: SearchConfig) -> io::Result<Self> {
+        Ok(Self {
+            config,
+            listener: TcpListener::bind(addr)?,
+            job_size: DEFAULT_JOB_SIZE,
+            lease: DEFAULT_LEASE,
+            progress: Progress::new(),
+        })
+    }
+
+    /// Quantity of numbers handed out to a worker at once.
+    pub fn job_size(mut self, job_size: usize) -> Self {
+        self.job_size = job_size.max(1);
+        self
+    }
+
+    /// Time after which the range of a worker without heartbeats
+    /// is handed out to another worker, at least MIN_LEASE.
+    pub fn lease(mut self, lease: Duration) -> Self {
+        self.lease = lease.max(MIN_LEASE);
+        self
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    pub fn config(&self) -> &SearchConfig {
+        &self.config
+    }
+
+    /// Counters of the running search.
+    pub fn progress(&self) -> Progress {
+        self.progress.clone()
+    }
+
+    /// Serves the workers until the quantity of hashes is reached,
+    /// the end of the numbers is reached or the stop token is triggered,
+    /// and calls on_match for every found hash like [`Finder::run_until`].
+    /// The stop token of the caller is only read. The transient errors
+    /// of the accepting are retried, the others end the search.
+    pub fn run_until<F: FnMut(Match)>(&self, stop: &StopToken, mut on_match: F) -> io::Result<()> {
+        self.listener.set_nonblocking(true)?;
+
+        let started = Instant::now();
+        let sequence = self.config.sequence();
+        let assignments = Mutex::new(Assignments::new(
+            self.config.first_index(),
+            sequence.limit(),
+            self.job_size,
+            self.lease,
+        ));
+        let mut collector = Collector::new(&self.config, &self.progress);
+        let (tx, rx) = channel();
+
+        let server = Server {
+            config: &self.config,
+            payload: self.config.payload(),
+            sequence,
+            assignments: &assignments,
+            heartbeat: self.lease / 3,
+            started,
+            stop,
+            over: StopToken::new(),
+        };
+
+        std::thread::scope(|scope| {
+            let server = &server;
+
+            let acceptor = scope.spawn(move || {
+                let mut connection = 0;
+
+                while !server.is_stopped() {
+                    match self.listener.accept() {
+                        Ok((stream, _)) => {
+                            connection += 1;
+                            let tx = tx.clone();
+                            scope.spawn(move || server.serve(stream, connection, tx));
+                        }
+                        Err(e) if e.kind() == ErrorKind::WouldBlock || is_transient(&e) => {
+                            std::thread::sleep(POLL_INTERVAL);
+                        }
+                        Err(e) => {
+                            server.over.stop();
+                            return Err(e);
+                        }
+                    }
+                }
+                Ok(())
+            });
+
+            while !collector.is_done() && !server.is_stopped() {
+                match rx.recv_timeout(POLL_INTERVAL) {
+                    Ok(Event::Found(m)) => collector.found(m, &mut on_match),
+                    Ok(Event::Complete(range)) => {
+                        self.progress.add_tried(range.len() as u64);
+                        collector.complete(range, &mut on_match);
+                    }
+                    Err(_) => {}
+                }
+            }
+
+            server.over.stop();
+            acceptor.join().unwrap()
+        })
+    }
+}
+
+/// Error of the accepting which passes, e.g. the connection closed
+/// by the client before the accepting or the limit of the open files.
+fn is_transient(error: &io::Error) -> bool {
+    matches!(
+        error.kind(),
+        ErrorKind::ConnectionAborted
+            | ErrorKind::ConnectionReset
+            | ErrorKind::Interrupted
+            | ErrorKind::TimedOut
+    ) || is_out_of_resources(error)
+}
+
+#[cfg(target_os = "linux")]
+fn is_out_of_resources(error: &io::Error) -> bool {
+    matches!(
+        error.raw_os_error(),
+        Some(libc::EMFILE | libc::ENFILE | libc::ENOBUFS | libc::ENOMEM)
+    )
+}
+
+#[cfg(not(target_os = "linux"))]
+fn is_out_of_resources(_error: &io::Error) -> bool {
+    false
+}
+
+/// Event of a connection to the search loop.
+enum Event {
+    Found(Match),
+    Complete(Range<usize>),
+}
+
+/// Ranges of indexes of the numbers leased to the workers.
+struct Assignments {
+    next: usize,
+    limit: usize,
+    size: usize,
+    lease: Duration,
+    requeued: Vec<Range<usize>>,
+    leases: HashMap<u64, Lease>,
+    last_job: u64,
+}
+
+struct Lease {
+    range: Range<usize>,
+    deadline: Instant,
+    connection: u64,
+}
+
+impl Assignments {
+    fn new(next: usize, limit: usize, size: usize, lease: Duration) -> Self {
+        Self {
+            next,
+            limit,
+            size,
+            lease,
+            requeued: Vec::new(),
+            leases: HashMap::new(),
+            last_job: 0,
+        }
+    }
+
+    /// Leases a range to the connection: the range of an expired lease
+    /// or the next range of the search.
+    fn claim(&mut self, connection: u64) -> Option<(u64, Range<usize>)> {
+        let now = Instant::now();
+
+        let expired: Vec<u64> = self
+            .leases
+            .iter()
+            .filter(|(_, lease)| lease.deadline < now)
+            .map(|(&job, _)| job)
+            .collect();
+        for job in expired {
+            if let Some(lease) = self.leases.remove(&job) {
+                self.requeued.push(lease.range);
+            }
+        }
+
+        let range = match self.requeued.pop() {
+            Some(range) => range,
+            None if self.next < self.limit => {
+                let start = self.next;
+                self.next = start.saturating_add(self.size).min(self.limit);
+                start..self.next
+            }
+            None => return None,
+        };
+
+        self.last_job += 1;
+        self.leases.insert(
+            self.last_job,
+            Lease {
+                range: range.clone(),
+                deadline: now + self.lease,
+                connection,
+            },
+        );
+
+        Some((self.last_job, range))
+    }
+
+    /// Extends the lease, false if the job is handed out to another worker.
+    fn renew(&mut self, job: u64) -> bool {
+        match self.leases.get_mut(&job) {
+            Some(lease) => {
+                lease.deadline = Instant::now() + self.lease;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// The index is in the range leased to the job.
+    fn is_leased(&self, job: u64, index: usize) -> bool {
+        self.leases
+            .get(&job)
+            .is_some_and(|lease| lease.range.contains(&index))
+    }
+
+    fn complete(&mut self, job: u64) -> Option<Range<usize>> {
+        self.leases.remove(&job).map(|lease| lease.range)
+    }
+
+    /// Hands out the ranges of the closed connection to other workers.
+    fn release(&mut self, connection: u64) {
+        let jobs: Vec<u64> = self
+            .leases
+            .iter()
+            .filter(|(_, lease)| lease.connection == connection)
+            .map(|(&job, _)| job)
+            .collect();
+
+        for job in jobs {
+            if let Some(lease) = self.leases.remove(&job) {
+                self.requeued.push(lease.range);
+            }
+        }
+    }
+}
+
+/// Shared state of the connections of the coordinator.
+struct Server<'a> {
+    config: &'a SearchConfig,
+    payload: Payload,
+    sequence: Sequence,
+    assignments: &'a Mutex<Assignments>,
+    heartbeat: Duration,
+    started: Instant,
+    stop: &'a StopToken,
+    /// triggered once the search is over
+    over: StopToken,
+}
+
+impl Server<'_> {
+    /// The search is stopped by the caller or is over.
+    fn is_stopped(&self) -> bool {
+        self.stop.is_stopped() || self.over.is_stopped()
+    }
+
+    /// Answers the requests of the worker until it disconnects
+    /// or the search is over.
+    fn serve(&self, stream: TcpStream, connection: u64, events: Sender<Event>) {
+        _ = self.serve_requests(stream, connection, events);
+        self.assignments.lock().unwrap().release(connection);
+    }
+
+    fn serve_requests(
+        &self,
+        stream: TcpStream,
+        connection: u64,
+        events: Sender<Event>,
+    ) -> io::Result<()> {
+        stream.set_nonblocking(false)?;
+        stream.set_read_timeout(Some(POLL_INTERVAL))?;
+
+        let mut writer = stream.try_clone()?;
+        let mut reader = BufReader::new(stream);
+        let mut line = String::new();
+
+        loop {
+            // The partial line stays in the buffer after the timeout.
+            match reader.read_line(&mut line) {
+                Ok(0) => return Ok(()),
+                Ok(_) => {}
+                Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                    if self.is_stopped() {
+                        return Ok(());
+                    }
+                    continue;
+                }
+                Err(e) => return Err(e),
+            }
+
+            let request = serde_json::from_str(&line)
+                .map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?;
+            line.clear();
+
+            let response = self.respond(request, connection, &events);
+            let stopped = matches!(response, Response::Stop);
+
+            send(&mut writer, &response)?;
+
+            if stopped {
+                return Ok(());
+            }
+        }
+    }
+
+    fn respond(&self, request: Request, connection: u64, events: &Sender<Event>) -> Response {
+        if self.is_stopped() {
+            return Response::Stop;
+        }
+
+        // Hashes of the workers are checked before they are reported,
+        // outside of the lock of the assignments.
+        let checked = match &request {
+            Request::Found { number, hash, .. } => {
+                check_hash(&self.payload, &self.config.predicate, *number).as_ref() == Some(hash)
+            }
+            _ => false,
+        };
+
+        let mut assignments = self.assignments.lock().unwrap();
+
+        match request {
+            Request::Claim => match assignments.claim(connection) {
+                Some((job, range)) => {
+                    let from = self.sequence.number(range.start);
+                    let to = self.sequence.number(range.end - 1).or(self.config.end);
+
+                    match from {
+                        Some(from) => Response::Job {
+                            job,
+                            config: self.config.clone(),
+                            from,
+                            to: to.unwrap_or(u128::MAX),
+                            heartbeat: self.heartbeat.as_millis() as u64,
+                        },
+                        None => Response::Stop,
+                    }
+                }
+                None if assignments.leases.is_empty() => Response::Stop,
+                None => Response::Wait {
+                    millis: self.heartbeat.as_millis() as u64,
+                },
+            },
+            Request::Found { job, number, hash } => {
+                // The number should be in the range leased to the worker.
+                let leased = self
+                    .sequence
+                    .position(number)
+                    .is_some_and(|index| assignments.is_leased(job, index));
+
+                if checked && leased {
+                    _ = events.send(Event::Found(Match {
+                        number,
+                        hash,
+                        elapsed: self.started.elapsed(),
+                    }));
+                }
+
+                if assignments.renew(job) {
+                    Response::Ack
+                } else {
+                    Response::Cancel
+                }
+            }
+            Request::Heartbeat { job } => {
+                if assignments.renew(job) {
+                    Response::Ack
+                } else {
+                    Response::Cancel
+                }
+            }
+            Request::Complete { job } => {
+                if let Some(range) = assignments.complete(job) {
+                    _ = events.send(Event::Complete(range));
+                }
+                Response::Ack
+            }
+        }
+    }
+}
+
+fn send<T: Serialize>(writer: &mut TcpStream, message: &T) -> io::Result<()> {
+    let mut data = serde_json::to_vec(message)?;
+    data.push(b'\n');
+    writer.write_all(&data)?;
+    writer.flush()
+}
+
+/// Connection of a worker to the coordinator.
+struct Connection {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+}
+
+impl Connection {
+    fn request(&mut self, request: &Request) -> io::Result<Response> {
+        send(&mut self.writer, request)?;
+
+        let mut line = String::new();
+        if self.reader.read_line(&mut line)? == 0 {
+            return Err(ErrorKind::UnexpectedEof.into());
+        }
+
+        serde_json::from_str(&line).map_err(|e| io::Error::new(ErrorKind::InvalidData, e))
+    }
+}
+
+/// Connects to the coordinator and processes the claimed ranges
+/// of numbers until the coordinator stops the search, closes
+/// the connection or the stop token is triggered.
+/// Out: quantity of completed jobs.
+pub fn run_worker<A: ToSocketAddrs>(addr: A, stop: &StopToken) -> io::Result<usize> {
+    let stream = TcpStream::connect(addr)?;
+    let connection = Mutex::new(Connection {
+        reader: BufReader::new(stream.try_clone()?),
+        writer: stream,
+    });
+
+    let mut completed = 0;
+
+    match process_jobs(&connection, stop, &mut completed) {
+        // The coordinator closes the connections at the end of the search.
+        Err(e)
+            if matches!(
+                e.kind(),
+                ErrorKind::UnexpectedEof
+                    | ErrorKind::ConnectionReset
+                    | ErrorKind::ConnectionAborted
+                    | ErrorKind::BrokenPipe
+            ) =>
+        {
+            Ok(completed)
+        }
+        result => result.map(|_| completed),
+    }
+}
+
+fn process_jobs(
+    connection: &Mutex<Connection>,
+    stop: &StopToken,
+    completed: &mut usize,
+) -> io::Result<()> {
+    let request = |request: &Request| connection.lock().unwrap().request(request);
+
+    while !stop.is_stopped() {
+        let (job, config, from, to, heartbeat) = match request(&Request::Claim)? {
+            Response::Job {
+                job,
+                config,
+                from,
+                to,
+                heartbeat,
+            } => (job, config, from, to, Duration::from_millis(heartbeat)),
+            Response::Wait { millis } => {
+                std::thread::sleep(Duration::from_millis(millis).min(POLL_INTERVAL * 10));
+                continue;
+            }
+            Response::Stop => break,
+            Response::Ack | Response::Cancel => continue,
+        };
+
+        // All hashes of the range are reported to the coordinator.
+        let finder = Finder::new(
+            config
+                .resume_from(Some(from))
+                .end(Some(to))
+                .hashes(usize::MAX)
+                .ordered(false),
+        );
+
+        let job_stop = StopToken::new();
+        let cancelled = AtomicBool::new(false);
+        let over = AtomicBool::new(false);
+        let mut error = None;
+
+        // Handles the response to the request about the job.
+        let handle = |response: io::Result<Response>| match response {
+            Ok(Response::Ack) => None,
+            Ok(Response::Cancel) => {
+                cancelled.store(true, Ordering::Relaxed);
+                job_stop.stop();
+                None
+            }
+            Ok(_) => {
+                over.store(true, Ordering::Relaxed);
+                job_stop.stop();
+                None
+            }
+            Err(e) => {
+                over.store(true, Ordering::Relaxed);
+                job_stop.stop();
+                Some(e)
+            }
+        };
+
+        std::thread::scope(|scope| {
+            let (done, finished) = channel::<()>();
+            let (handle, over, job_stop) = (&handle, &over, &job_stop);
+
+            let heartbeats = scope.spawn(move || {
+                let mut last = Instant::now();
+
+                while let Err(RecvTimeoutError::Timeout) = finished.recv_timeout(POLL_INTERVAL) {
+                    if stop.is_stopped() {
+                        over.store(true, Ordering::Relaxed);
+                        job_stop.stop();
+                    } else if last.elapsed() >= heartbeat {
+                        last = Instant::now();
+                        if let Some(e) = handle(request(&Request::Heartbeat { job })) {
+                            return Some(e);
+                        }
+                    }
+                }
+                None
+            });
+
+            finder.run_until(job_stop, |m| {
+                if error.is_none() {
+                    error = handle(request(&Request::Found {
+                        job,
+                        number: m.number,
+                        hash: m.hash,
+                    }));
+                }
+            });
+
+            drop(done);
+            if let Some(e) = heartbeats.join().unwrap() {
+                error.get_or_insert(e);
+            }
+        });
+
+        if let Some(e) = error {
+            return Err(e);
+        }
+        if over.load(Ordering::Relaxed) {
+            break;
+        }
+        if !cancelled.load(Ordering::Relaxed) {
+            request(&Request::Complete { job })?;
+            *completed += 1;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_assignments_lease() {
+        let mut assignments = Assignments::new(0, 25, 10, Duration::ZERO);
+
+        assert_eq!(assignments.claim(1), Some((1, 0..10)));
+        // The lease of the job 1 is expired.
+        assert_eq!(assignments.claim(2), Some((2, 0..10)));
+        assert!(!assignments.renew(1));
+        assert_eq!(assignments.complete(2), Some(0..10));
+
+        assignments.lease = Duration::from_secs(60);
+        assert_eq!(assignments.claim(1), Some((3, 10..20)));
+        assert_eq!(assignments.claim(2), Some((4, 20..25)));
+        assert_eq!(assignments.claim(2), None);
+
+        assignments.release(1);
+        assert_eq!(assignments.claim(2), Some((5, 10..20)));
+    }
+
+    #[test]
+    fn test_server_found() {
+        let config = SearchConfig::new(2, 1).step(2);
+        let sequence = config.sequence();
+        let assignments = Mutex::new(Assignments::new(0, sequence.limit(), 1000, DEFAULT_LEASE));
+        let stop = StopToken::new();
+        let server = Server {
+            config: &config,
+            payload: config.payload(),
+            sequence,
+            assignments: &assignments,
+            heartbeat: DEFAULT_LEASE,
+            started: Instant::now(),
+            stop: &stop,
+            over: StopToken::new(),
+        };
+        let (tx, rx) = channel();
+
+        // The odd numbers 1..=1999 are leased to the job 1.
+        let response = server.respond(Request::Claim, 1, &tx);
+        assert!(matches!(response, Response::Job { job: 1, from: 1, to: 1999, .. }));
+
+        let found = |job, number| Request::Found {
+            job,
+            number,
+            hash: hex::encode(config.payload().digest(number)),
+        };
+
+        // The number of another step, out of the range, of an unknown job.
+        for request in [found(1, 932), found(1, 2081), found(2, 403)] {
+            server.respond(request, 1, &tx);
+        }
+        let forged = Request::Found {
+            job: 1,
+            number: 403,
+            hash: "00".repeat(32),
+        };
+        server.respond(forged, 1, &tx);
+        assert!(rx.try_recv().is_err());
+
+        server.respond(found(1, 403), 1, &tx);
+        assert!(matches!(rx.try_recv(), Ok(Event::Found(m)) if m.number == 403));
+    }
+
+    #[test]
+    fn test_coordinator_workers() {
+        let config = SearchConfig::new(3, 6).ordered(true).chunk_size(100);
+        let coordinator = Coordinator::bind("127.0.0.1:0", config)
+            .unwrap()
+            .job_size(1000)
+            .lease(Duration::ZERO);
+        let addr = coordinator.local_addr().unwrap();
+        assert_eq!(coordinator.lease, MIN_LEASE);
+
+        let stop = StopToken::new();
+        let mut found = Vec::new();
+
+        std::thread::scope(|scope| {
+            for _ in 0..2 {
+                scope.spawn(|| run_worker(addr, &StopToken::new()).unwrap());
+            }
+
+            coordinator
+                .run_until(&stop, |m| found.push(m.number))
+                .unwrap();
+        });
+
+        assert_eq!(found, vec![4163, 11848, 12843, 13467, 20215, 28892]);
+    }
+
+    #[test]
+    fn test_coordinator_reassign() {
+        let config = SearchConfig::new(3, 2).ordered(true).chunk_size(100);
+        let coordinator = Coordinator::bind("127.0.0.1:0", config)
+            .unwrap()
+            .job_size(1000);
+        let addr = coordinator.local_addr().unwrap();
+
+        let stop = StopToken::new();
+        let mut found = Vec::new();
+
+        std::thread::scope(|scope| {
+            // The worker claims the first range and disconnects
+            // before the other worker connects.
+            let claimed = scope.spawn(|| {
+                let stream = TcpStream::connect(addr).unwrap();
+                let mut connection = Connection {
+                    reader: BufReader::new(stream.try_clone().unwrap()),
+                    writer: stream,
+                };
+                let response = connection.request(&Request::Claim).unwrap();
+                assert!(matches!(response, Response::Job { from: 1, .. }));
+            });
+
+            scope.spawn(|| {
+                claimed.join().unwrap();
+                run_worker(addr, &StopToken::new()).unwrap()
+            });
+
+            coordinator
+                .run_until(&stop, |m| found.push(m.number))
+                .unwrap();
+        });
+
+        assert_eq!(found, vec![4163, 11848]);
+    }
+}
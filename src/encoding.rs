@@ -0,0 +1,284 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+/// Largest length of an encoded number including the padding.
+pub const MAX_ENCODED_LEN: usize = 64;
+
+const DIGITS: &[u8; 36] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+
+/// Representation of the numbers in the hashed data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Encoding {
+    /// decimal text
+    #[default]
+    Dec,
+    /// lowercase hexadecimal text
+    Hex,
+    /// lowercase text of the digits 0-9 and a-z
+    Base36,
+    /// raw bytes of the number as an unsigned integer of the size,
+    /// little-endian or big-endian
+    Le32,
+    Be32,
+    Le64,
+    Be64,
+    Le128,
+    Be128,
+}
+
+impl Encoding {
+    pub const ALL: [Encoding; 9] = [
+        Encoding::Dec,
+        Encoding::Hex,
+        Encoding::Base36,
+        Encoding::Le32,
+        Encoding::Be32,
+        Encoding::Le64,
+        Encoding::Be64,
+        Encoding::Le128,
+        Encoding::Be128,
+    ];
+
+    /// Name of the encoding used by the command line.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Encoding::Dec => "dec",
+            Encoding::Hex => "hex",
+            Encoding::Base36 => "base36",
+            Encoding::Le32 => "le32",
+            Encoding::Be32 => "be32",
+            Encoding::Le64 => "le64",
+            Encoding::Be64 => "be64",
+            Encoding::Le128 => "le128",
+            Encoding::Be128 => "be128",
+        }
+    }
+
+    /// Text encodings can be padded with zeros.
+    pub fn is_text(&self) -> bool {
+        matches!(self, Encoding::Dec | Encoding::Hex | Encoding::Base36)
+    }
+
+    /// Largest number representable by the encoding.
+    pub fn max_number(&self) -> u128 {
+        match self {
+            Encoding::Le32 | Encoding::Be32 => u32::MAX as u128,
+            Encoding::Le64 | Encoding::Be64 => u64::MAX as u128,
+            _ => u128::MAX,
+        }
+    }
+}
+
+impl fmt::Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Encoding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Encoding::ALL
+            .into_iter()
+            .find(|e| e.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| {
+                let names: Vec<_> = Encoding::ALL.iter().map(|e| e.name()).collect();
+                format!(
+                    "unknown encoding {s}, expected one of: {}",
+                    names.join(", ")
+                )
+            })
+    }
+}
+
+/// Writes the raw bytes to the end of the buffer.
+/// Out: index of the first byte.
+fn write_bytes<const N: usize>(bytes: [u8; N], buffer: &mut [u8; MAX_ENCODED_LEN]) -> usize {
+    buffer[MAX_ENCODED_LEN - N..].copy_from_slice(&bytes);
+    MAX_ENCODED_LEN - N
+}
+
+/// Writes the digits of the number to the end of the buffer,
+/// the constant radix keeps the division cheap. The 128-bit division
+/// is used only for the digits above 64 bits.
+/// Out: index of the first digit.
+fn write_digits<const RADIX: u64>(number: u128, buffer: &mut [u8; MAX_ENCODED_LEN]) -> usize {
+    let mut start = MAX_ENCODED_LEN;
+    let mut wide = number;
+
+    while wide > u64::MAX as u128 {
+        start -= 1;
+        buffer[start] = DIGITS[(wide % RADIX as u128) as usize];
+        wide /= RADIX as u128;
+    }
+
+    let mut number = wide as u64;
+    loop {
+        start -= 1;
+        buffer[start] = DIGITS[(number % RADIX) as usize];
+        number /= RADIX;
+
+        if number == 0 {
+            return start;
+        }
+    }
+}
+
+/// Encoding of the numbers with the zero padding of the text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct NumberFormat {
+    pub encoding: Encoding,
+    /// smallest length of the text, at most MAX_ENCODED_LEN,
+    /// the binary encodings have the fixed size
+    #[serde(default)]
+    pub width: usize,
+}
+
+impl NumberFormat {
+    pub fn new(encoding: Encoding, width: usize) -> Self {
+        Self { encoding, width }
+    }
+
+    /// Writes the representation of the number to the end
+    /// of the buffer without allocations, the binary encodings
+    /// keep the low bytes of a number above their maximum.
+    /// Out: the representation, a part of the buffer.
+    pub fn write(self, number: u128, buffer: &mut [u8; MAX_ENCODED_LEN]) -> &[u8] {
+        let mut start = match self.encoding {
+            Encoding::Dec => write_digits::<10>(number, buffer),
+            Encoding::Hex => write_digits::<16>(number, buffer),
+            Encoding::Base36 => write_digits::<36>(number, buffer),
+            Encoding::Le32 => write_bytes((number as u32).to_le_bytes(), buffer),
+            Encoding::Be32 => write_bytes((number as u32).to_be_bytes(), buffer),
+            Encoding::Le64 => write_bytes((number as u64).to_le_bytes(), buffer),
+            Encoding::Be64 => write_bytes((number as u64).to_be_bytes(), buffer),
+            Encoding::Le128 => write_bytes(number.to_le_bytes(), buffer),
+            Encoding::Be128 => write_bytes(number.to_be_bytes(), buffer),
+        };
+
+        let padded = MAX_ENCODED_LEN - self.width.min(MAX_ENCODED_LEN);
+        if self.encoding.is_text() && padded < start {
+            buffer[padded..start].fill(b'0');
+            start = padded;
+        }
+        &buffer[start..]
+    }
+
+    /// Representation of the number.
+    pub fn encode(self, number: u128) -> Vec<u8> {
+        let mut buffer = [0; MAX_ENCODED_LEN];
+        self.write(number, &mut buffer).to_vec()
+    }
+}
+
+impl fmt::Display for NumberFormat {
+    /// Format specification of the template placeholder, e.g. 08x.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.width > 0 {
+            write!(f, "0{}", self.width)?;
+        }
+        match self.encoding {
+            Encoding::Dec => Ok(()),
+            Encoding::Hex => f.write_str("x"),
+            Encoding::Base36 => f.write_str("b36"),
+            binary => f.write_str(binary.name()),
+        }
+    }
+}
+
+impl FromStr for NumberFormat {
+    type Err = String;
+
+    /// Parses the specification: optional width of the zero padding
+    /// followed by nothing or d for decimal, x for hex, b36 for base36,
+    /// or the name of a binary encoding without the width, e.g. le64.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid number format {s}, expected e.g. 08, x, 016x, b36");
+
+        let digits = s.len() - s.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+        let (width, encoding) = s.split_at(digits);
+
+        let width = match width {
+            "" => 0,
+            width => width.parse().map_err(|_| invalid())?,
+        };
+        if width > MAX_ENCODED_LEN {
+            return Err(format!(
+                "number width {width} is larger than {MAX_ENCODED_LEN}"
+            ));
+        }
+
+        let encoding = match encoding {
+            "" | "d" => Encoding::Dec,
+            "x" => Encoding::Hex,
+            "b36" => Encoding::Base36,
+            binary => match binary.parse::<Encoding>() {
+                Ok(encoding) if !encoding.is_text() && width == 0 => encoding,
+                _ => return Err(invalid()),
+            },
+        };
+
+        Ok(Self::new(encoding, width))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encoding_from_str() {
+        for encoding in Encoding::ALL {
+            assert_eq!(encoding.name().parse::<Encoding>(), Ok(encoding));
+        }
+        assert!("le16".parse::<Encoding>().is_err());
+    }
+
+    #[test]
+    fn test_number_format() {
+        for number in [0, 7, 35, 4163, u64::MAX as u128, 1 << 64, u128::MAX] {
+            assert_eq!(
+                NumberFormat::default().encode(number),
+                number.to_string().as_bytes()
+            );
+            assert_eq!(
+                NumberFormat::new(Encoding::Hex, 8).encode(number),
+                format!("{number:08x}").as_bytes()
+            );
+        }
+
+        let base36 = NumberFormat::new(Encoding::Base36, 0);
+        assert_eq!(base36.encode(35), b"z");
+        assert_eq!(base36.encode(36 * 36 + 1), b"101");
+        assert_eq!(base36.encode(u64::MAX as u128), b"3w5e11264sgsf");
+        assert_eq!(base36.encode(u128::MAX), b"f5lxx1zz5pnorynqglhzmsp33");
+
+        let binary = |encoding| NumberFormat::new(encoding, 0).encode(0x0102_0304);
+        assert_eq!(binary(Encoding::Le32), [4, 3, 2, 1]);
+        assert_eq!(binary(Encoding::Be32), [1, 2, 3, 4]);
+        assert_eq!(binary(Encoding::Le64), [4, 3, 2, 1, 0, 0, 0, 0]);
+        assert_eq!(binary(Encoding::Be64), [0, 0, 0, 0, 1, 2, 3, 4]);
+        assert_eq!(binary(Encoding::Le128), 0x0102_0304u128.to_le_bytes());
+        assert_eq!(binary(Encoding::Be128), 0x0102_0304u128.to_be_bytes());
+        assert_eq!(Encoding::Le32.max_number(), u32::MAX as u128);
+
+        let wide = NumberFormat::new(Encoding::Dec, 100);
+        assert_eq!(wide.encode(5).len(), MAX_ENCODED_LEN);
+    }
+
+    #[test]
+    fn test_number_format_from_str() {
+        for spec in ["", "08", "x", "016x", "b36", "06b36", "le32", "be128"] {
+            assert_eq!(spec.parse::<NumberFormat>().unwrap().to_string(), spec);
+        }
+        assert_eq!("d".parse::<NumberFormat>(), Ok(NumberFormat::default()));
+        assert!("8y".parse::<NumberFormat>().is_err());
+        assert!("65".parse::<NumberFormat>().is_err());
+        assert!("08le32".parse::<NumberFormat>().is_err());
+        assert!("dec".parse::<NumberFormat>().is_err());
+    }
+}
@@ -0,0 +1,1005 @@
+use std::collections::{BTreeMap, HashSet};
+use std::ops::Range;
+use std::sync::mpsc::*;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use rayon::{Scope, ThreadPool};
+use serde::{Deserialize, Serialize};
+
+use crate::batch::{Sha256Batch, MAX_LANES};
+use crate::encoding::{NumberFormat, MAX_ENCODED_LEN};
+use crate::hasher::{Algorithm, MAX_OUTPUT_SIZE};
+use crate::payload::{Payload, Template};
+use crate::predicate::{Position, Predicate};
+use crate::progress::Progress;
+use crate::scheduler::{Chunks, Frontier, Sequence};
+use crate::shard::Shard;
+use crate::stop::StopToken;
+
+/// Default quantity of numbers handed out to a worker at once.
+pub const DEFAULT_CHUNK_SIZE: usize = 4096;
+
+/// Parameters of the search.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SearchConfig {
+    /// condition which the hash should satisfy
+    pub predicate: Predicate,
+    /// quantity of hashes to find
+    pub hashes: usize,
+    /// report the smallest found numbers in ascending order
+    pub ordered: bool,
+    /// quantity of numbers handed out to a worker at once
+    pub chunk_size: usize,
+    /// hash algorithm of the search
+    pub algorithm: Algorithm,
+    /// first number of the search
+    pub start: u128,
+    /// last number of the search, unbounded if none
+    pub end: Option<u128>,
+    /// distance between the numbers of the search
+    pub step: usize,
+    /// subset of the numbers processed by this search
+    pub shard: Shard,
+    /// number to continue the interrupted search from,
+    /// the numbers below it are skipped
+    pub resume_from: Option<u128>,
+    /// constant start of the hashed data, the number follows it
+    #[serde(default)]
+    pub header: Vec<u8>,
+    /// representation of the number in the hashed data
+    #[serde(default)]
+    pub format: NumberFormat,
+    /// constant end of the hashed data after the number
+    #[serde(default)]
+    pub trailer: Vec<u8>,
+}
+
+impl SearchConfig {
+    /// In: nulls - quantity of nulls at the end of hash,
+    /// hashes - quantity of hashes to find.
+    pub fn new(nulls: usize, hashes: usize) -> Self {
+        Self {
+            predicate: Predicate::zeros(nulls, Position::Suffix),
+            hashes,
+            ordered: false,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            algorithm: Algorithm::default(),
+            start: 1,
+            end: None,
+            step: 1,
+            shard: Shard::default(),
+            resume_from: None,
+            header: Vec::new(),
+            format: NumberFormat::default(),
+            trailer: Vec::new(),
+        }
+    }
+
+    /// In ordered mode the search reports the F smallest numbers
+    /// in ascending order instead of the order of tasks completion.
+    pub fn ordered(mut self, ordered: bool) -> Self {
+        self.ordered = ordered;
+        self
+    }
+
+    pub fn chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = chunk_size;
+        self
+    }
+
+    pub fn algorithm(mut self, algorithm: Algorithm) -> Self {
+        self.algorithm = algorithm;
+        self
+    }
+
+    pub fn predicate(mut self, predicate: Predicate) -> Self {
+        self.predicate = predicate;
+        self
+    }
+
+    pub fn hashes(mut self, hashes: usize) -> Self {
+        self.hashes = hashes;
+        self
+    }
+
+    pub fn start(mut self, start: u128) -> Self {
+        self.start = start;
+        self
+    }
+
+    pub fn end(mut self, end: Option<u128>) -> Self {
+        self.end = end;
+        self
+    }
+
+    pub fn step(mut self, step: usize) -> Self {
+        self.step = step;
+        self
+    }
+
+    pub fn shard(mut self, shard: Shard) -> Self {
+        self.shard = shard;
+        self
+    }
+
+    pub fn resume_from(mut self, resume_from: Option<u128>) -> Self {
+        self.resume_from = resume_from;
+        self
+    }
+
+    pub fn header(mut self, header: Vec<u8>) -> Self {
+        self.header = header;
+        self
+    }
+
+    pub fn format(mut self, format: NumberFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    pub fn trailer(mut self, trailer: Vec<u8>) -> Self {
+        self.trailer = trailer;
+        self
+    }
+
+    /// Template around the numbers, it sets the header,
+    /// the format and the trailer.
+    pub fn template(self, template: Template) -> Self {
+        self.header(template.prefix)
+            .format(template.format)
+            .trailer(template.suffix)
+    }
+
+    /// Hashing of the data of the numbers, the header is hashed once.
+    pub fn payload(&self) -> Payload {
+        Payload::new(self.algorithm, &self.header, self.format, &self.trailer)
+    }
+
+    /// Numbers of the search, an unbounded search ends
+    /// at the largest number of the encoding.
+    pub(crate) fn sequence(&self) -> Sequence {
+        let max = self.format.encoding.max_number();
+        let end = match self.end {
+            None if max == u128::MAX => None,
+            end => Some(end.unwrap_or(max).min(max)),
+        };
+
+        Sequence::new(self.start, end, self.step, self.shard)
+    }
+
+    /// Largest number of the search, none if there are no numbers.
+    /// An unbounded search ends at the largest number of the encoding,
+    /// or of u128, or after 2^64 - 1 numbers. The numbers never wrap.
+    pub fn last_number(&self) -> Option<u128> {
+        let sequence = self.sequence();
+        sequence.number(sequence.limit().checked_sub(1)?)
+    }
+
+    /// The numbers are hashed in batches of SHA-256, see [`Sha256Batch`].
+    pub(crate) fn is_batched(&self) -> bool {
+        self.algorithm == Algorithm::Sha256 && self.trailer.len() <= BATCH_TRAILER_LEN
+    }
+
+    /// Quantity of numbers of the search from the number to resume from.
+    pub fn numbers(&self) -> u64 {
+        let sequence = self.sequence();
+        sequence.limit().saturating_sub(self.first_index()) as u64
+    }
+
+    /// Index of the first number to process.
+    pub(crate) fn first_index(&self) -> usize {
+        self.sequence()
+            .index_of(self.resume_from.unwrap_or(self.start))
+    }
+}
+
+/// Number and its hash found by the search.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Match {
+    pub number: u128,
+    pub hash: String,
+    /// time from the start of the search to the discovery
+    #[serde(skip)]
+    pub elapsed: Duration,
+}
+
+/// Searches numbers whose hash satisfies the configured predicate.
+#[derive(Debug, Clone)]
+pub struct Finder {
+    config: SearchConfig,
+    progress: Progress,
+    /// pool of the workers, the global rayon pool if none
+    pool: Option<Arc<ThreadPool>>,
+}
+
+impl Finder {
+    pub fn new(config: SearchConfig) -> Self {
+        Self {
+            config,
+            progress: Progress::new(),
+            pool: None,
+        }
+    }
+
+    /// Runs the workers in the pool instead of the global rayon pool.
+    pub fn thread_pool(mut self, pool: Arc<ThreadPool>) -> Self {
+        self.pool = Some(pool);
+        self
+    }
+
+    pub fn config(&self) -> &SearchConfig {
+        &self.config
+    }
+
+    /// Counters of the running search, they are reset at the start
+    /// of every search.
+    pub fn progress(&self) -> Progress {
+        self.progress.clone()
+    }
+
+    /// Runs the search in the current thread and calls on_match
+    /// for every found hash until the quantity of hashes is reached.
+    pub fn run<F: FnMut(Match)>(&self, on_match: F) {
+        self.run_until(&StopToken::new(), on_match);
+    }
+
+    /// Runs the search until the quantity of hashes is reached,
+    /// the end of the numbers is reached or the stop token is triggered.
+    /// The numbers are handed out to the workers by contiguous ranges,
+    /// the bounded channel of reports keeps the workers from running
+    /// ahead of the consumer. Once the search is over the workers are
+    /// stopped without finishing their ranges, the stop token of the caller
+    /// is only read.
+    pub fn run_until<F: FnMut(Match)>(&self, stop: &StopToken, mut on_match: F) {
+        let config = &self.config;
+        let started = Instant::now();
+        let sequence = config.sequence();
+        let first = config.first_index();
+        let progress = &self.progress;
+        let mut collector = Collector::new(config, progress);
+
+        let threads = match &self.pool {
+            Some(pool) => pool.current_num_threads(),
+            None => rayon::current_num_threads(),
+        };
+        let chunks = Chunks::new(first, sequence.limit(), config.chunk_size);
+        let (tx, rx) = sync_channel(threads * 2);
+        let over = StopToken::new();
+
+        in_place_scope(self.pool.as_deref(), |scope| {
+            for _ in 0..threads {
+                let tx = tx.clone();
+                let chunks = &chunks;
+                let sequence = &sequence;
+                let stops = [stop, &over];
+                scope.spawn(move |_| worker(chunks, sequence, config, started, stops, progress, tx));
+            }
+            drop(tx);
+
+            while !collector.is_done() {
+                match rx.recv() {
+                    Ok(Report::Found(m)) => collector.found(m, &mut on_match),
+                    Ok(Report::Done(range)) => collector.complete(range, &mut on_match),
+                    Err(_) => break,
+                }
+            }
+
+            over.stop();
+            drop(rx);
+        });
+    }
+
+    /// Runs the search in a background thread and returns
+    /// an iterator over the found hashes.
+    /// Dropping the iterator stops the search.
+    pub fn iter(&self) -> Matches {
+        let (tx, rx) = channel();
+        let finder = self.clone();
+        let stop = StopToken::new();
+
+        std::thread::spawn({
+            let stop = stop.clone();
+            move || {
+                finder.run_until(&stop, |found| {
+                    _ = tx.send(found);
+                });
+            }
+        });
+
+        Matches { rx, stop }
+    }
+
+    /// Processes all numbers up to the end regardless of the quantity
+    /// of hashes and calls on_match for every found hash, until
+    /// the stop token is triggered. The end of the numbers should be set.
+    /// Out: quantity of found hashes.
+    pub fn count_until<F: FnMut(Match)>(&self, stop: &StopToken, mut on_match: F) -> usize {
+        let finder = Finder {
+            config: self.config.clone().hashes(usize::MAX),
+            ..self.clone()
+        };
+
+        let mut count = 0;
+        finder.run_until(stop, |m| {
+            count += 1;
+            on_match(m);
+        });
+        count
+    }
+}
+
+impl IntoIterator for &Finder {
+    type Item = Match;
+    type IntoIter = Matches;
+
+    fn into_iter(self) -> Matches {
+        self.iter()
+    }
+}
+
+/// Iterator over the hashes found by [`Finder::iter`].
+pub struct Matches {
+    rx: Receiver<Match>,
+    stop: StopToken,
+}
+
+impl Iterator for Matches {
+    type Item = Match;
+
+    fn next(&mut self) -> Option<Match> {
+        self.rx.recv().ok()
+    }
+}
+
+impl Drop for Matches {
+    fn drop(&mut self) {
+        self.stop.stop();
+    }
+}
+
+/// Reports the found hashes of the ranges of indexes, which are
+/// processed in any order, until the quantity of hashes is reached.
+/// In ordered mode the found hashes wait until all numbers below them
+/// are processed. The hashes found more than once are reported once.
+pub(crate) struct Collector<'a> {
+    sequence: Sequence,
+    frontier: Frontier,
+    limit: usize,
+    /// found hashes of the ordered search by the indexes of the numbers
+    found: BTreeMap<usize, Match>,
+    reported: HashSet<u128>,
+    ordered: bool,
+    hashes: usize,
+    progress: &'a Progress,
+}
+
+impl<'a> Collector<'a> {
+    /// Resets the progress to the start of the search.
+    pub(crate) fn new(config: &SearchConfig, progress: &'a Progress) -> Self {
+        let sequence = config.sequence();
+        let first = config.first_index();
+
+        progress.reset(number_before_end(&sequence, first));
+
+        Self {
+            sequence,
+            frontier: Frontier::new(first),
+            limit: sequence.limit(),
+            found: BTreeMap::new(),
+            reported: HashSet::new(),
+            ordered: config.ordered,
+            hashes: config.hashes,
+            progress,
+        }
+    }
+
+    /// The quantity of hashes is reached or all numbers are processed.
+    pub(crate) fn is_done(&self) -> bool {
+        self.reported.len() >= self.hashes || self.frontier.next() >= self.limit
+    }
+
+    pub(crate) fn found<F: FnMut(Match)>(&mut self, m: Match, on_match: &mut F) {
+        if self.is_done() || self.reported.contains(&m.number) {
+            return;
+        }
+
+        if self.ordered {
+            self.found.insert(self.sequence.index_of(m.number), m);
+        } else {
+            self.report(m, on_match);
+        }
+    }
+
+    pub(crate) fn complete<F: FnMut(Match)>(&mut self, range: Range<usize>, on_match: &mut F) {
+        if range.start < self.frontier.next() {
+            // The range is already completed by another worker.
+            return;
+        }
+
+        self.frontier.complete(range);
+        let next = self.frontier.next();
+
+        while self.ordered && self.reported.len() < self.hashes {
+            match self.found.first_entry() {
+                Some(entry) if *entry.key() < next => {
+                    let m = entry.remove();
+                    self.report(m, on_match);
+                }
+                _ => break,
+            }
+        }
+
+        // The found hashes below the frontier are reported
+        // before the frontier is published.
+        self.progress
+            .set_next(number_before_end(&self.sequence, next));
+    }
+
+    fn report<F: FnMut(Match)>(&mut self, m: Match, on_match: &mut F) {
+        self.reported.insert(m.number);
+        self.progress.add_found();
+        on_match(m);
+    }
+}
+
+/// Report of a worker to the search loop.
+enum Report {
+    Found(Match),
+    Done(Range<usize>),
+}
+
+/// Runs the operation in a scope of the pool, of the global pool if none.
+fn in_place_scope<'scope, OP, R>(pool: Option<&ThreadPool>, op: OP) -> R
+where
+    OP: FnOnce(&Scope<'scope>) -> R,
+{
+    match pool {
+        Some(pool) => pool.in_place_scope(op),
+        None => rayon::in_place_scope(op),
+    }
+}
+
+/// Number by its index, none if it is after all numbers of the search.
+fn number_before_end(sequence: &Sequence, index: usize) -> Option<u128> {
+    sequence.number(index).filter(|_| index < sequence.limit())
+}
+
+/// Claims ranges of numbers and checks them until one of the stop tokens
+/// is triggered.
+#[allow(clippy::too_many_arguments)]
+fn worker(
+    chunks: &Chunks,
+    sequence: &Sequence,
+    config: &SearchConfig,
+    started: Instant,
+    stops: [&StopToken; 2],
+    progress: &Progress,
+    tx: SyncSender<Report>,
+) {
+    let checker = Checker::new(config);
+    let lanes = checker.lanes();
+    let mut found = Vec::new();
+
+    while let Some(range) = chunks.claim() {
+        let mut index = range.start;
+
+        while index < range.end {
+            if stops.iter().any(|stop| stop.is_stopped()) {
+                return;
+            }
+
+            let mut numbers = [0; MAX_LANES];
+            let mut count = 0;
+
+            while count < lanes && index < range.end {
+                let Some(number) = sequence.number(index) else {
+                    index = range.end;
+                    break;
+                };
+                numbers[count] = number;
+                count += 1;
+                index += 1;
+            }
+
+            checker.check(&config.predicate, &numbers[..count], &mut found);
+
+            for (number, hash) in found.drain(..) {
+                let elapsed = started.elapsed();
+
+                if tx
+                    .send(Report::Found(Match {
+                        number,
+                        hash,
+                        elapsed,
+                    }))
+                    .is_err()
+                {
+                    return;
+                }
+            }
+        }
+
+        progress.add_tried(range.len() as u64);
+
+        if tx.send(Report::Done(range)).is_err() {
+            return;
+        }
+    }
+}
+
+/// Largest trailer of the batched SHA-256, the data of every lane
+/// is joined on the stack.
+const BATCH_TRAILER_LEN: usize = 64;
+
+/// Hashing of the numbers of the workers, SHA-256 is hashed
+/// in batches by the fastest backend of the CPU.
+#[allow(clippy::large_enum_variant)]
+enum Checker {
+    Scalar(Payload),
+    Batch(Sha256Batch, NumberFormat, Vec<u8>),
+}
+
+impl Checker {
+    fn new(config: &SearchConfig) -> Self {
+        if config.is_batched() {
+            Checker::Batch(
+                Sha256Batch::new(&config.header),
+                config.format,
+                config.trailer.clone(),
+            )
+        } else {
+            Checker::Scalar(config.payload())
+        }
+    }
+
+    /// Quantity of numbers checked at once.
+    fn lanes(&self) -> usize {
+        match self {
+            Checker::Scalar(_) => 1,
+            Checker::Batch(batch, ..) => batch.lanes(),
+        }
+    }
+
+    /// Checks the numbers, at most MAX_LANES of them.
+    /// Out: found - the matched numbers and hashes are appended.
+    fn check(&self, predicate: &Predicate, numbers: &[u128], found: &mut Vec<(u128, String)>) {
+        match self {
+            Checker::Scalar(payload) => found.extend(
+                numbers
+                    .iter()
+                    .filter_map(|&n| Some((n, check_hash(payload, predicate, n)?))),
+            ),
+            Checker::Batch(batch, format, trailer) => {
+                let mut data = [[0; MAX_ENCODED_LEN + BATCH_TRAILER_LEN]; MAX_LANES];
+                let mut sizes = [0; MAX_LANES];
+                let mut digests = [[0; 32]; MAX_LANES];
+                let mut text = [0; MAX_ENCODED_LEN];
+
+                for ((data, size), &number) in data.iter_mut().zip(&mut sizes).zip(numbers) {
+                    let text = format.write(number, &mut text);
+                    *size = text.len() + trailer.len();
+                    data[..text.len()].copy_from_slice(text);
+                    data[text.len()..*size].copy_from_slice(trailer);
+                }
+
+                let count = numbers.len();
+                let messages: [&[u8]; MAX_LANES] =
+                    std::array::from_fn(|lane| &data[lane][..sizes[lane]]);
+                batch.digest(&messages[..count], &mut digests[..count]);
+
+                for (&number, digest) in numbers.iter().zip(&digests) {
+                    if predicate.matches(digest) {
+                        found.push((number, hex::encode(digest)));
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Computing and checking hash of number.
+/// In: payload - hashing of the data of the number,
+/// predicate - condition which the hash should satisfy,
+/// number - target to hashing.
+/// Out: hash in successful case.
+pub fn check_hash(payload: &Payload, predicate: &Predicate, number: u128) -> Option<String> {
+    // The digest stays on the stack, only the found hashes are encoded.
+    let mut digest = [0; MAX_OUTPUT_SIZE];
+
+    let size = payload.digest_to(number, &mut digest);
+    let digest = &digest[..size];
+
+    if predicate.matches(digest) {
+        Some(hex::encode(digest))
+    } else {
+        None
+    }
+}
+
+/// Computing and checking sha256 hash of number for nulls at the end.
+/// In: number - target to hashing,
+/// nulls - quantity of nulls at the end of hash,
+/// tx - sends number and hash in successful case.
+pub fn process_hash(number: usize, nulls: usize, tx: Sender<(usize, String)>) {
+    let predicate = Predicate::zeros(nulls, Position::Suffix);
+
+    if let Some(hash) = check_hash(&Algorithm::Sha256.into(), &predicate, number as u128) {
+        _ = tx.send((number, hash));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encoding::Encoding;
+    use crate::hasher::Hasher;
+
+    #[test]
+    fn test_calculate_hash_value_1() {
+        let (tx, rx) = channel();
+
+        let number = 4163;
+        process_hash(number, 3, tx.clone());
+
+        if let Ok(result) = rx.try_recv() {
+            assert_eq!(
+                (result.0, result.1.as_str()),
+                (
+                    number,
+                    "95d4362bd3cd4315d0bbe38dfa5d7fb8f0aed5f1a31d98d510907279194e3000"
+                )
+            );
+        } else {
+            panic!();
+        }
+    }
+
+    #[test]
+    fn test_calculate_hash_value_2() {
+        let (tx, rx) = channel();
+
+        let number = 828028;
+        process_hash(number, 5, tx.clone());
+
+        if let Ok(result) = rx.try_recv() {
+            assert_eq!(
+                (result.0, result.1.as_str()),
+                (
+                    number,
+                    "d95f19b5269418c0d4479fa61b8e7696aa8df197082b431a65ff37595c100000"
+                )
+            );
+        } else {
+            panic!();
+        }
+    }
+
+    #[test]
+    #[allow(clippy::useless_conversion, clippy::redundant_pattern_matching)]
+    fn test_calculate_hash_quantity_1() {
+        let (tx, rx) = channel();
+
+        let sucessful_nums = vec![4163, 11848, 12843, 13467, 20215, 28892];
+        let sucessful_qnt = sucessful_nums.len();
+
+        let unsucessful_nums = vec![1, 2, 3, 4, 5];
+
+        sucessful_nums
+            .into_iter()
+            .chain(unsucessful_nums.into_iter())
+            .for_each(|v| process_hash(v, 3, tx.clone()));
+
+        let mut result_qnt = 0;
+
+        while let Ok(_) = rx.try_recv() {
+            result_qnt += 1;
+        }
+
+        assert_eq!(result_qnt, sucessful_qnt);
+    }
+
+    #[test]
+    #[allow(clippy::useless_conversion, clippy::redundant_pattern_matching)]
+    fn test_calculate_hash_quantity_2() {
+        let (tx, rx) = channel();
+
+        let sucessful_nums = vec![828028, 2513638, 3063274];
+        let sucessful_qnt = sucessful_nums.len();
+
+        let unsucessful_nums = vec![1, 2, 3, 4, 5];
+
+        sucessful_nums
+            .into_iter()
+            .chain(unsucessful_nums.into_iter())
+            .for_each(|v| process_hash(v, 5, tx.clone()));
+
+        let mut result_qnt = 0;
+
+        while let Ok(_) = rx.try_recv() {
+            result_qnt += 1;
+        }
+
+        assert_eq!(result_qnt, sucessful_qnt);
+    }
+
+    #[test]
+    fn test_finder_run() {
+        let finder = Finder::new(SearchConfig::new(3, 2));
+
+        let mut found = Vec::new();
+        finder.run(|m| found.push(m));
+
+        assert_eq!(found.len(), 2);
+        assert!(found.iter().all(|m| m.hash.ends_with("000")));
+    }
+
+    #[test]
+    fn test_finder_run_ordered() {
+        let finder = Finder::new(SearchConfig::new(3, 4).ordered(true));
+
+        let mut found = Vec::new();
+        finder.run(|m| found.push(m.number));
+
+        assert_eq!(found, vec![4163, 11848, 12843, 13467]);
+    }
+
+    #[test]
+    fn test_finder_run_ordered_small_chunks() {
+        let finder = Finder::new(SearchConfig::new(3, 6).ordered(true).chunk_size(7));
+
+        let mut found = Vec::new();
+        finder.run(|m| found.push(m.number));
+
+        assert_eq!(found, vec![4163, 11848, 12843, 13467, 20215, 28892]);
+    }
+
+    #[test]
+    fn test_finder_run_algorithm() {
+        let config = SearchConfig::new(2, 3)
+            .ordered(true)
+            .algorithm(Algorithm::Md5);
+
+        let mut found = Vec::new();
+        Finder::new(config).run(|m| found.push(m));
+
+        assert_eq!(found.len(), 3);
+        for m in found {
+            assert_eq!(
+                m.hash,
+                Algorithm::Md5.hex_digest(m.number.to_string().as_bytes())
+            );
+            assert!(m.hash.ends_with("00"));
+        }
+    }
+
+    #[test]
+    fn test_finder_run_pattern() {
+        let config = SearchConfig::new(0, 2)
+            .ordered(true)
+            .predicate(Predicate::pattern("00", "0").unwrap());
+
+        let mut found = Vec::new();
+        Finder::new(config).run(|m| found.push(m));
+
+        assert_eq!(found.len(), 2);
+        assert!(found[0].number < found[1].number);
+        assert!(found
+            .iter()
+            .all(|m| m.hash.starts_with("00") && m.hash.ends_with('0')));
+    }
+
+    #[test]
+    fn test_finder_progress() {
+        let finder = Finder::new(SearchConfig::new(3, 2).ordered(true).chunk_size(100));
+        let progress = finder.progress();
+
+        finder.run(|_| {});
+
+        assert_eq!(progress.found(), 2);
+        assert!(progress.tried() >= 11848);
+    }
+
+    #[test]
+    fn test_finder_run_start() {
+        let finder = Finder::new(SearchConfig::new(3, 2).ordered(true).start(12000));
+        let progress = finder.progress();
+
+        let mut found = Vec::new();
+        finder.run(|m| found.push(m.number));
+
+        assert_eq!(found, vec![12843, 13467]);
+        assert!(progress.next().unwrap() > 13467);
+    }
+
+    #[test]
+    fn test_finder_run_end_step() {
+        let config = SearchConfig::new(3, 10)
+            .ordered(true)
+            .chunk_size(100)
+            .start(4163)
+            .end(Some(28892))
+            .step(5);
+
+        let mut found = Vec::new();
+        Finder::new(config).run(|m| found.push(m.number));
+
+        // Successful numbers 4163 + 5 * k up to the end.
+        assert_eq!(found, vec![4163, 11848, 12843]);
+    }
+
+    #[test]
+    fn test_finder_run_header() {
+        let header = vec![0xab; 150];
+        let config = SearchConfig::new(2, 3).header(header.clone());
+
+        let mut found = Vec::new();
+        Finder::new(config).run(|m| found.push(m));
+
+        assert_eq!(found.len(), 3);
+        for m in found {
+            let data = [&header[..], m.number.to_string().as_bytes()].concat();
+            assert_eq!(m.hash, sha256::digest(data.as_slice()));
+            assert!(m.hash.ends_with("00"));
+        }
+    }
+
+    #[test]
+    fn test_finder_run_template() {
+        let template: Template = "user=alice;nonce={n:06x};ts=1700000000".parse().unwrap();
+
+        for algorithm in [Algorithm::Sha256, Algorithm::Md5] {
+            let config = SearchConfig::new(2, 3)
+                .algorithm(algorithm)
+                .template(template.clone());
+
+            let mut found = Vec::new();
+            Finder::new(config).run(|m| found.push(m));
+
+            assert_eq!(found.len(), 3);
+            for m in found {
+                let data = format!("user=alice;nonce={:06x};ts=1700000000", m.number);
+                assert_eq!(m.hash, algorithm.hex_digest(data.as_bytes()));
+                assert!(m.hash.ends_with("00"));
+            }
+        }
+    }
+
+    #[test]
+    fn test_finder_run_binary() {
+        let format = NumberFormat::new(Encoding::Be32, 0);
+        let config = SearchConfig::new(2, 100)
+            .header(b"head".to_vec())
+            .format(format)
+            .trailer(vec![0xff, 0x00])
+            .start(u32::MAX as u128 - 3000);
+
+        let mut found = Vec::new();
+        Finder::new(config).run(|m| found.push(m));
+
+        // The search ends at the largest 32-bit number before 100 matches.
+        assert!(!found.is_empty() && found.len() < 100);
+        for m in found {
+            assert!(m.number <= u32::MAX as u128);
+            let data = [
+                &b"head"[..],
+                &(m.number as u32).to_be_bytes(),
+                &[0xff, 0x00],
+            ]
+            .concat();
+            assert_eq!(m.hash, sha256::digest(data.as_slice()));
+        }
+    }
+
+    #[test]
+    fn test_finder_run_u128() {
+        let start = u128::MAX - 2000;
+        let config = SearchConfig::new(2, 100).start(start).step(3);
+        assert_eq!(config.last_number(), Some(u128::MAX - 2));
+
+        let mut found = Vec::new();
+        Finder::new(config).run(|m| found.push(m));
+
+        // The search stops at the last number instead of wrapping around.
+        assert!(!found.is_empty() && found.len() < 100);
+        for m in found {
+            assert!(m.number >= start && (m.number - start) % 3 == 0);
+            assert_eq!(m.hash, sha256::digest(m.number.to_string()));
+        }
+
+        assert_eq!(
+            SearchConfig::new(2, 1).last_number(),
+            Some(u64::MAX as u128)
+        );
+        let empty = SearchConfig::new(2, 1).start(10).end(Some(5));
+        assert_eq!(empty.last_number(), None);
+    }
+
+    #[test]
+    fn test_finder_run_last_number() {
+        // The hash of u128::MAX ends with 3e75.
+        let config = SearchConfig::new(0, 1)
+            .predicate(Predicate::pattern("", "3e75").unwrap())
+            .start(u128::MAX - 100);
+
+        for ordered in [false, true] {
+            let finder = Finder::new(config.clone().ordered(ordered));
+            let mut found = Vec::new();
+            finder.run(|m| found.push(m.number));
+
+            assert_eq!(found, vec![u128::MAX]);
+        }
+
+        let finder = Finder::new(config.clone().ordered(true).hashes(2));
+        finder.run(|_| {});
+        assert_eq!(finder.progress().next(), None);
+    }
+
+    #[test]
+    fn test_finder_count() {
+        let config = SearchConfig::new(3, 1).chunk_size(100).end(Some(20000));
+        assert_eq!(config.numbers(), 20000);
+        assert_eq!(config.clone().resume_from(Some(5001)).numbers(), 15000);
+        let finder = Finder::new(config);
+
+        let mut found = Vec::new();
+        let count = finder.count_until(&StopToken::new(), |m| found.push(m.number));
+        found.sort();
+
+        assert_eq!(count, 4);
+        assert_eq!(found, vec![4163, 11848, 12843, 13467]);
+        assert_eq!(finder.progress().tried(), 20000);
+    }
+
+    #[test]
+    fn test_finder_run_shards() {
+        let config = SearchConfig::new(3, 6).ordered(true).chunk_size(100);
+
+        let mut found = Vec::new();
+        for i in 0..3 {
+            let shard = Shard::new(i, 3)
+                .unwrap()
+                .mode(crate::shard::ShardMode::Blocks(1000));
+            Finder::new(config.clone().shard(shard)).run(|m| found.push(m.number));
+        }
+        found.sort();
+        found.truncate(6);
+
+        assert_eq!(found, vec![4163, 11848, 12843, 13467, 20215, 28892]);
+    }
+
+    #[test]
+    fn test_finder_run_stopped() {
+        let finder = Finder::new(SearchConfig::new(3, 2));
+
+        let stop = StopToken::new();
+        stop.stop();
+
+        let mut found = Vec::new();
+        finder.run_until(&stop, |m| found.push(m));
+
+        assert!(found.is_empty());
+    }
+
+    #[test]
+    fn test_finder_run_keeps_token() {
+        let finder = Finder::new(SearchConfig::new(3, 2));
+        let stop = StopToken::new();
+
+        let mut found = Vec::new();
+        finder.run_until(&stop, |m| found.push(m));
+
+        assert_eq!(found.len(), 2);
+        assert!(!stop.is_stopped());
+    }
+
+    #[test]
+    fn test_finder_iter() {
+        let finder = Finder::new(SearchConfig::new(3, 2));
+
+        let found: Vec<Match> = finder.iter().collect();
+
+        assert_eq!(found.len(), 2);
+        assert!(found.iter().all(|m| m.hash.ends_with("000")));
+    }
+}
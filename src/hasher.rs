@@ -0,0 +1,336 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+use sha2::Digest;
+
+/// Largest size of the digests of the supported algorithms in bytes.
+pub const MAX_OUTPUT_SIZE: usize = 64;
+
+/// Calculates the digest of the data.
+pub trait Hasher {
+    /// Size of the digest in bytes.
+    fn output_size(&self) -> usize;
+
+    /// Raw bytes of the digest.
+    fn digest(&self, data: &[u8]) -> Vec<u8>;
+
+    /// Writes the raw bytes of the digest to the start of the buffer,
+    /// the algorithms override it to avoid allocations.
+    /// Out: size of the digest.
+    fn digest_to(&self, data: &[u8], buffer: &mut [u8; MAX_OUTPUT_SIZE]) -> usize {
+        let digest = self.digest(data);
+        buffer[..digest.len()].copy_from_slice(&digest);
+        digest.len()
+    }
+
+    /// Character representation of the digest.
+    fn hex_digest(&self, data: &[u8]) -> String {
+        hex::encode(self.digest(data))
+    }
+}
+
+/// Supported hash algorithms.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Algorithm {
+    Sha1,
+    Sha224,
+    #[default]
+    Sha256,
+    Sha384,
+    Sha512,
+    #[serde(rename = "sha3-256")]
+    Sha3_256,
+    Blake2b,
+    Blake3,
+    Md5,
+}
+
+impl Algorithm {
+    pub const ALL: [Algorithm; 9] = [
+        Algorithm::Sha1,
+        Algorithm::Sha224,
+        Algorithm::Sha256,
+        Algorithm::Sha384,
+        Algorithm::Sha512,
+        Algorithm::Sha3_256,
+        Algorithm::Blake2b,
+        Algorithm::Blake3,
+        Algorithm::Md5,
+    ];
+
+    /// Name of the algorithm used by the command line.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Algorithm::Sha1 => "sha1",
+            Algorithm::Sha224 => "sha224",
+            Algorithm::Sha256 => "sha256",
+            Algorithm::Sha384 => "sha384",
+            Algorithm::Sha512 => "sha512",
+            Algorithm::Sha3_256 => "sha3-256",
+            Algorithm::Blake2b => "blake2b",
+            Algorithm::Blake3 => "blake3",
+            Algorithm::Md5 => "md5",
+        }
+    }
+}
+
+impl Hasher for Algorithm {
+    fn output_size(&self) -> usize {
+        match self {
+            Algorithm::Sha1 => 20,
+            Algorithm::Sha224 => 28,
+            Algorithm::Sha256 => 32,
+            Algorithm::Sha384 => 48,
+            Algorithm::Sha512 => 64,
+            Algorithm::Sha3_256 => 32,
+            Algorithm::Blake2b => 64,
+            Algorithm::Blake3 => 32,
+            Algorithm::Md5 => 16,
+        }
+    }
+
+    fn digest(&self, data: &[u8]) -> Vec<u8> {
+        match self {
+            Algorithm::Sha1 => sha1::Sha1::digest(data).to_vec(),
+            Algorithm::Sha224 => sha2::Sha224::digest(data).to_vec(),
+            Algorithm::Sha256 => sha2::Sha256::digest(data).to_vec(),
+            Algorithm::Sha384 => sha2::Sha384::digest(data).to_vec(),
+            Algorithm::Sha512 => sha2::Sha512::digest(data).to_vec(),
+            Algorithm::Sha3_256 => sha3::Sha3_256::digest(data).to_vec(),
+            Algorithm::Blake2b => blake2::Blake2b512::digest(data).to_vec(),
+            Algorithm::Blake3 => blake3::hash(data).as_bytes().to_vec(),
+            Algorithm::Md5 => md5::Md5::digest(data).to_vec(),
+        }
+    }
+
+    fn digest_to(&self, data: &[u8], buffer: &mut [u8; MAX_OUTPUT_SIZE]) -> usize {
+        let mut write = |digest: &[u8]| {
+            buffer[..digest.len()].copy_from_slice(digest);
+            digest.len()
+        };
+
+        match self {
+            Algorithm::Sha1 => write(&sha1::Sha1::digest(data)),
+            Algorithm::Sha224 => write(&sha2::Sha224::digest(data)),
+            Algorithm::Sha256 => write(&sha2::Sha256::digest(data)),
+            Algorithm::Sha384 => write(&sha2::Sha384::digest(data)),
+            Algorithm::Sha512 => write(&sha2::Sha512::digest(data)),
+            Algorithm::Sha3_256 => write(&sha3::Sha3_256::digest(data)),
+            Algorithm::Blake2b => write(&blake2::Blake2b512::digest(data)),
+            Algorithm::Blake3 => write(blake3::hash(data).as_bytes()),
+            Algorithm::Md5 => write(&md5::Md5::digest(data)),
+        }
+    }
+}
+
+/// State of the algorithm after the constant header of the data.
+/// The compression of the header blocks is done once, the digests
+/// of the data continue from the state, so a long header costs about
+/// the same per digest as a short one.
+#[derive(Debug, Clone)]
+#[allow(clippy::large_enum_variant)]
+pub enum Midstate {
+    Sha1(sha1::Sha1),
+    Sha224(sha2::Sha224),
+    Sha256(sha2::Sha256),
+    Sha384(sha2::Sha384),
+    Sha512(sha2::Sha512),
+    Sha3_256(sha3::Sha3_256),
+    Blake2b(blake2::Blake2b512),
+    Blake3(blake3::Hasher),
+    Md5(md5::Md5),
+}
+
+impl Midstate {
+    /// In: header - constant start of the hashed data.
+    pub fn new(algorithm: Algorithm, header: &[u8]) -> Self {
+        match algorithm {
+            Algorithm::Sha1 => Midstate::Sha1(sha1::Sha1::new_with_prefix(header)),
+            Algorithm::Sha224 => Midstate::Sha224(sha2::Sha224::new_with_prefix(header)),
+            Algorithm::Sha256 => Midstate::Sha256(sha2::Sha256::new_with_prefix(header)),
+            Algorithm::Sha384 => Midstate::Sha384(sha2::Sha384::new_with_prefix(header)),
+            Algorithm::Sha512 => Midstate::Sha512(sha2::Sha512::new_with_prefix(header)),
+            Algorithm::Sha3_256 => Midstate::Sha3_256(sha3::Sha3_256::new_with_prefix(header)),
+            Algorithm::Blake2b => Midstate::Blake2b(blake2::Blake2b512::new_with_prefix(header)),
+            Algorithm::Blake3 => {
+                let mut hasher = blake3::Hasher::new();
+                hasher.update(header);
+                Midstate::Blake3(hasher)
+            }
+            Algorithm::Md5 => Midstate::Md5(md5::Md5::new_with_prefix(header)),
+        }
+    }
+
+    pub fn algorithm(&self) -> Algorithm {
+        match self {
+            Midstate::Sha1(_) => Algorithm::Sha1,
+            Midstate::Sha224(_) => Algorithm::Sha224,
+            Midstate::Sha256(_) => Algorithm::Sha256,
+            Midstate::Sha384(_) => Algorithm::Sha384,
+            Midstate::Sha512(_) => Algorithm::Sha512,
+            Midstate::Sha3_256(_) => Algorithm::Sha3_256,
+            Midstate::Blake2b(_) => Algorithm::Blake2b,
+            Midstate::Blake3(_) => Algorithm::Blake3,
+            Midstate::Md5(_) => Algorithm::Md5,
+        }
+    }
+}
+
+/// Digest of the header and the parts of the data, the state stays unchanged.
+fn finish<D: Digest + Clone>(state: &D, parts: &[&[u8]], buffer: &mut [u8]) -> usize {
+    let mut state = state.clone();
+    for part in parts {
+        state.update(part);
+    }
+    let digest = state.finalize();
+    buffer[..digest.len()].copy_from_slice(&digest);
+    digest.len()
+}
+
+impl Hasher for Midstate {
+    fn output_size(&self) -> usize {
+        self.algorithm().output_size()
+    }
+
+    fn digest(&self, data: &[u8]) -> Vec<u8> {
+        let mut buffer = [0; MAX_OUTPUT_SIZE];
+        let size = self.digest_to(data, &mut buffer);
+        buffer[..size].to_vec()
+    }
+
+    /// Digest of the header followed by the data.
+    fn digest_to(&self, data: &[u8], buffer: &mut [u8; MAX_OUTPUT_SIZE]) -> usize {
+        self.digest_parts_to(&[data], buffer)
+    }
+}
+
+impl Midstate {
+    /// Writes the digest of the header followed by the parts of the data
+    /// without joining them.
+    /// Out: size of the digest.
+    pub fn digest_parts_to(&self, parts: &[&[u8]], buffer: &mut [u8; MAX_OUTPUT_SIZE]) -> usize {
+        match self {
+            Midstate::Sha1(state) => finish(state, parts, buffer),
+            Midstate::Sha224(state) => finish(state, parts, buffer),
+            Midstate::Sha256(state) => finish(state, parts, buffer),
+            Midstate::Sha384(state) => finish(state, parts, buffer),
+            Midstate::Sha512(state) => finish(state, parts, buffer),
+            Midstate::Sha3_256(state) => finish(state, parts, buffer),
+            Midstate::Blake2b(state) => finish(state, parts, buffer),
+            Midstate::Blake3(state) => {
+                let mut hasher = state.clone();
+                for part in parts {
+                    hasher.update(part);
+                }
+                buffer[..32].copy_from_slice(hasher.finalize().as_bytes());
+                32
+            }
+            Midstate::Md5(state) => finish(state, parts, buffer),
+        }
+    }
+}
+
+impl fmt::Display for Algorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Algorithm {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Algorithm::ALL
+            .into_iter()
+            .find(|a| a.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| {
+                let names: Vec<_> = Algorithm::ALL.iter().map(|a| a.name()).collect();
+                format!(
+                    "unknown algorithm {s}, expected one of: {}",
+                    names.join(", ")
+                )
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sha256_digest() {
+        assert_eq!(
+            Algorithm::Sha256.hex_digest(b"4163"),
+            sha256::digest("4163")
+        );
+    }
+
+    #[test]
+    fn test_digest_values() {
+        assert_eq!(
+            Algorithm::Sha1.hex_digest(b"abc"),
+            "a9993e364706816aba3e25717850c26c9cd0d89d"
+        );
+        assert_eq!(
+            Algorithm::Md5.hex_digest(b"abc"),
+            "900150983cd24fb0d6963f7d28e17f72"
+        );
+        assert_eq!(
+            Algorithm::Sha3_256.hex_digest(b"abc"),
+            "3a985da74fe225b2045c172d6bd390bd855f086e3e9d525b46bfe24511431532"
+        );
+        assert_eq!(
+            Algorithm::Blake3.hex_digest(b"abc"),
+            "6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85"
+        );
+    }
+
+    #[test]
+    fn test_output_size() {
+        for algorithm in Algorithm::ALL {
+            assert_eq!(algorithm.digest(b"abc").len(), algorithm.output_size());
+        }
+    }
+
+    #[test]
+    fn test_digest_to() {
+        let mut buffer = [0; MAX_OUTPUT_SIZE];
+
+        for algorithm in Algorithm::ALL {
+            let size = algorithm.digest_to(b"abc", &mut buffer);
+            assert_eq!(&buffer[..size], algorithm.digest(b"abc"));
+        }
+    }
+
+    #[test]
+    fn test_midstate() {
+        let header: Vec<u8> = (0..200).map(|i| i as u8).collect();
+
+        for algorithm in Algorithm::ALL {
+            let midstate = Midstate::new(algorithm, &header);
+
+            for tail in [&b""[..], b"4163", b"12345678901234567890"] {
+                let data = [&header[..], tail].concat();
+                assert_eq!(midstate.digest(tail), algorithm.digest(&data));
+
+                let mut buffer = [0; MAX_OUTPUT_SIZE];
+                let size = midstate.digest_parts_to(&[tail, b"", b"ts=1"], &mut buffer);
+                assert_eq!(
+                    &buffer[..size],
+                    algorithm.digest(&[&data[..], b"ts=1"].concat())
+                );
+            }
+            assert_eq!(midstate.algorithm(), algorithm);
+        }
+    }
+
+    #[test]
+    fn test_from_str() {
+        for algorithm in Algorithm::ALL {
+            assert_eq!(algorithm.name().parse::<Algorithm>(), Ok(algorithm));
+        }
+        assert!("sha0".parse::<Algorithm>().is_err());
+    }
+}
@@ -0,0 +1,49 @@
+//! Search engine of the hash_finder application.
+//! Iterates over integers starting from 1, calculates the hash
+//! (sha256 by default) for each of the numbers and reports the numbers whose hash digest
+//! ends in N-characters of zero.
+//!
+//! Usage example:
+//! ```no_run
+//! use hash_finder::{Finder, SearchConfig};
+//!
+//! let finder = Finder::new(SearchConfig::new(5, 3));
+//! finder.run(|found| println!("{}, {}", found.number, found.hash));
+//! ```
+
+mod api;
+mod batch;
+mod bench;
+mod checkpoint;
+mod cluster;
+mod encoding;
+mod finder;
+mod hasher;
+mod output;
+mod payload;
+mod pool;
+mod predicate;
+mod progress;
+mod scheduler;
+mod shard;
+mod stop;
+mod verify;
+
+pub use api::ApiServer;
+pub use batch::{Backend, Sha256Batch, MAX_LANES};
+pub use bench::{bench, measure_stages, measure_throughput, BenchResult, Stages, Throughput};
+pub use checkpoint::Checkpoint;
+pub use cluster::{run_worker, Coordinator, DEFAULT_JOB_SIZE, DEFAULT_LEASE, MIN_LEASE};
+pub use encoding::{Encoding, NumberFormat, MAX_ENCODED_LEN};
+pub use finder::{
+    check_hash, process_hash, Finder, Match, Matches, SearchConfig, DEFAULT_CHUNK_SIZE,
+};
+pub use hasher::{Algorithm, Hasher, Midstate, MAX_OUTPUT_SIZE};
+pub use output::{Format, Output};
+pub use payload::{Payload, Template};
+pub use pool::{configure_pool, pin_thread, CpuList};
+pub use predicate::{Position, Predicate};
+pub use progress::Progress;
+pub use shard::{Shard, ShardMode};
+pub use stop::StopToken;
+pub use verify::{verify_line, verify_lines, Mismatch};
@@ -1,158 +1,855 @@
 use argh::FromArgs;
-use std::sync::mpsc::*;
+use hash_finder::{
+    bench, configure_pool, run_worker, verify_lines, Algorithm, ApiServer, BenchResult, Checkpoint,
+    Coordinator, CpuList, Encoding, Finder, Format, Hasher, NumberFormat, Output, Payload,
+    Position, Predicate, Progress, SearchConfig, Shard, ShardMode, StopToken, Template,
+    DEFAULT_CHUNK_SIZE, DEFAULT_JOB_SIZE,
+};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Interval of the progress reports.
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(1);
 
 #[derive(FromArgs)]
 /// The application iterates over integers starting from 1,
-/// calculates the sha256 hash for each of the numbers, and
+/// calculates the hash (sha256 by default) for each of the numbers, and
 /// displays the hash and the original number to the console
 /// if the hash digest (character representation of the hash)
 /// ends in N-characters of zero. The F parameter determines
 /// how many hash values the command should find.
 /// Usage example: hash_finder -N 5 -F 3
 struct Args {
+    #[argh(subcommand)]
+    command: Option<Command>,
+
     /// quantity of nulls at the end of hash
     #[argh(option, short = 'N')]
-    nulls: u32,
+    nulls: Option<u32>,
+
+    /// quantity of zero bits of the raw hash, instead of -N
+    #[argh(option)]
+    bits: Option<u32>,
+
+    /// hex target, the hash as a big-endian integer should be below it
+    #[argh(option)]
+    target: Option<String>,
+
+    /// compact nBits target in hex, e.g. 1d00ffff
+    #[argh(option)]
+    nbits: Option<String>,
+
+    /// position of the nulls or zero bits: prefix, suffix or both
+    #[argh(option, default = "Position::default()")]
+    position: Position,
+
+    /// hex pattern at the start of hash, instead of -N
+    #[argh(option)]
+    prefix: Option<String>,
+
+    /// hex pattern at the end of hash, instead of -N
+    #[argh(option)]
+    suffix: Option<String>,
+
+    /// constant data in hex hashed before the number,
+    /// e.g. a block header
+    #[argh(option)]
+    header: Option<String>,
+
+    /// data hashed around the number, e.g. nonce={{n}};ts=1700000000,
+    /// the placeholder may have a format: {{n:08}}, {{n:x}}, {{n:b36}}
+    #[argh(option)]
+    template: Option<String>,
+
+    /// encoding of the number: dec, hex, base36, le32, be32, le64, be64,
+    /// le128, be128, decimal text by default
+    #[argh(option)]
+    encoding: Option<Encoding>,
+
+    /// constant data in hex hashed after the number
+    #[argh(option)]
+    trailer: Option<String>,
 
     /// quantity of hashes to find
     #[argh(option, short = 'F')]
-    hashes: u32,
+    hashes: Option<u32>,
+
+    /// print the F smallest numbers in ascending order
+    #[argh(switch)]
+    ordered: bool,
+
+    /// quantity of numbers handed out to a worker at once
+    #[argh(option, default = "DEFAULT_CHUNK_SIZE")]
+    chunk_size: usize,
+
+    /// first number of the search, up to 2^128 - 1
+    #[argh(option, default = "1")]
+    start: u128,
+
+    /// last number of the search, unbounded by default
+    #[argh(option)]
+    end: Option<u128>,
+
+    /// distance between the numbers of the search
+    #[argh(option, default = "1")]
+    step: usize,
+
+    /// process only the shard i of n of the numbers, e.g. 2/8
+    #[argh(option)]
+    shard: Option<Shard>,
+
+    /// split the numbers between the shards in blocks of the size
+    /// instead of interleaving them
+    #[argh(option)]
+    shard_block: Option<usize>,
+
+    /// hash algorithm: sha1, sha224, sha256, sha384, sha512, sha3-256,
+    /// blake2b, blake3, md5
+    #[argh(option, default = "Algorithm::default()")]
+    algorithm: Algorithm,
+
+    /// output format: text, json, ndjson, csv
+    #[argh(option, default = "Format::default()")]
+    format: Format,
+
+    /// quantity of worker threads, the quantity of CPUs by default
+    #[argh(option)]
+    threads: Option<usize>,
+
+    /// CPUs to pin the worker threads to, e.g. 0-3,6 (Linux only)
+    #[argh(option)]
+    affinity: Option<CpuList>,
+
+    /// print the progress of the search to stderr every second
+    #[argh(switch)]
+    progress: bool,
+
+    /// file to save the state of the search periodically
+    #[argh(option)]
+    checkpoint: Option<PathBuf>,
+
+    /// interval of the checkpoint saving in seconds
+    #[argh(option, default = "10")]
+    checkpoint_interval: u64,
+
+    /// continue the search from the checkpoint
+    #[argh(switch)]
+    resume: bool,
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand)]
+enum Command {
+    Serve(Serve),
+    Worker(Worker),
+    Api(Api),
+    Verify(Verify),
+    Count(Count),
+    Bench(Bench),
+}
+
+#[derive(FromArgs)]
+/// Hand out the ranges of numbers of the search to the workers
+/// connected over TCP and print the hashes found by them.
+/// Usage example: hash_finder -N 5 -F 3 serve --listen 127.0.0.1:7878
+#[argh(subcommand, name = "serve")]
+struct Serve {
+    /// address to accept the workers on
+    #[argh(option, default = "String::from(\"127.0.0.1:7878\")")]
+    listen: String,
+
+    /// quantity of numbers handed out to a worker at once
+    #[argh(option, default = "DEFAULT_JOB_SIZE")]
+    job_size: usize,
+
+    /// seconds without heartbeats after which the range of a worker
+    /// is handed out to another worker, positive
+    #[argh(option, default = "30")]
+    lease: u64,
+}
+
+#[derive(FromArgs)]
+/// Process the ranges of numbers handed out by the coordinator.
+/// Usage example: hash_finder worker --connect 127.0.0.1:7878
+#[argh(subcommand, name = "worker")]
+struct Worker {
+    /// address of the coordinator
+    #[argh(option)]
+    connect: String,
+}
+
+#[derive(FromArgs)]
+/// Run the searches submitted over the local HTTP/JSON API:
+/// POST /jobs, GET /jobs, GET /jobs/ID, GET /jobs/ID/matches,
+/// DELETE /jobs/ID.
+/// Usage example: hash_finder api --listen 127.0.0.1:8080
+#[argh(subcommand, name = "api")]
+struct Api {
+    /// address to accept the requests on
+    #[argh(option, default = "String::from(\"127.0.0.1:8080\")")]
+    listen: String,
+}
+
+#[derive(FromArgs)]
+/// Check "number, hash" lines of the output: the hash should be
+/// the digest of the number and satisfy the condition of the search,
+/// e.g. -N. The failed lines are listed and the exit code is 1.
+/// Usage example: hash_finder -N 5 verify found.txt
+#[argh(subcommand, name = "verify")]
+struct Verify {
+    /// file with the lines to check, stdin by default
+    #[argh(positional)]
+    file: Option<PathBuf>,
+}
+
+#[derive(FromArgs)]
+/// Count all numbers from --start to --end whose hash satisfies
+/// the condition of the search and compare the density of them
+/// with the theoretical probability, e.g. 16^-N for -N.
+/// Usage example: hash_finder -N 4 --end 10000000 count --quiet
+#[argh(subcommand, name = "count")]
+struct Count {
+    /// don't print the found hashes, only the totals
+    #[argh(switch)]
+    quiet: bool,
+}
+
+#[derive(FromArgs)]
+/// Measure the hashes per second of the search with the quantities
+/// of threads and the chunk sizes, and the cost of the formatting,
+/// hashing and checking of a number. The condition is -N 5 by default.
+/// Usage example: hash_finder bench --threads 1 --threads 4 --json bench.json
+#[argh(subcommand, name = "bench")]
+struct Bench {
+    /// quantity of threads, repeatable, powers of two up to the CPUs by default
+    #[argh(option)]
+    threads: Vec<usize>,
+
+    /// chunk size, repeatable, 1024, 4096 and 16384 by default
+    #[argh(option)]
+    chunk_size: Vec<usize>,
+
+    /// quantity of numbers of every measure
+    #[argh(option, default = "1_000_000")]
+    numbers: usize,
+
+    /// measure every algorithm instead of --algorithm
+    #[argh(switch)]
+    all_algorithms: bool,
+
+    /// file to write the results as JSON to
+    #[argh(option)]
+    json: Option<PathBuf>,
 }
 
 fn main() {
     let args: Args = argh::from_env();
 
-    let mut current_number = 1;
+    match &args.command {
+        Some(Command::Worker(worker)) => {
+            setup_pool(&args);
+            return work(worker);
+        }
+        Some(Command::Api(api)) => {
+            setup_pool(&args);
+            return serve_api(api);
+        }
+        Some(Command::Verify(verify_args)) => return verify(&args, verify_args),
+        _ => {}
+    }
 
-    let mut complete_tasks = 0;
-    let max_complete_tasks: usize = args.hashes as usize;
+    if let Some(Command::Bench(bench_args)) = &args.command {
+        return run_bench(&args, bench_args);
+    }
 
-    let nulls = args.nulls as usize;
+    let hashes = match &args.command {
+        Some(Command::Count(_)) => 0,
+        _ => args.hashes.unwrap_or_else(|| fail("-F is required")),
+    };
+    let predicate = predicate(&args).unwrap_or_else(|e| fail(&e));
+
+    let config = SearchConfig::new(0, hashes as usize)
+        .predicate(predicate)
+        .ordered(args.ordered)
+        .chunk_size(args.chunk_size)
+        .algorithm(args.algorithm)
+        .template(template(&args))
+        .start(args.start)
+        .end(args.end)
+        .step(args.step)
+        .shard(shard(&args));
+
+    if args.step == 0 {
+        fail("--step should be positive");
+    }
 
-    rayon::scope(|scope| {
-        let (tx, rx) = channel();
+    if let Some(Command::Serve(serve_args)) = &args.command {
+        return serve(&args, serve_args, config);
+    }
+
+    setup_pool(&args);
+
+    if let Some(Command::Count(count_args)) = &args.command {
+        return count(&args, count_args, config);
+    }
 
-        while complete_tasks < max_complete_tasks {
-            if rayon::current_num_threads() < rayon::max_num_threads() {
-                let tx = tx.clone();
-                scope.spawn(move |_| {
-                    process_hash(current_number, nulls, tx);
-                });
+    let checkpoint = match (&args.checkpoint, args.resume) {
+        (Some(path), true) => Checkpoint::load(path)
+            .unwrap_or_else(|e| fail(&format!("can't load checkpoint {}: {e}", path.display()))),
+        (None, true) => fail("--resume requires --checkpoint"),
+        _ => Checkpoint::new(&config),
+    };
+
+    let finder = Finder::new(checkpoint.resume(&config).unwrap_or_else(|e| fail(&e)));
+    let progress = finder.progress();
+    let checkpoint = Arc::new(Mutex::new(checkpoint));
+
+    let mut background = Background::new(&args, &progress, Goal::hashes(finder.config()));
+
+    if let Some(path) = args.checkpoint.clone() {
+        let progress = progress.clone();
+        let checkpoint = checkpoint.clone();
+        let interval = Duration::from_secs(args.checkpoint_interval);
+        background.spawn(move |done| {
+            periodically(interval, done, || {
+                save_checkpoint(&mut checkpoint.lock().unwrap(), &progress, &path)
+            })
+        });
+    }
+
+    let mut output = Output::new(std::io::stdout().lock(), args.format, &config);
+    output.begin().unwrap_or_else(|e| fail(&e.to_string()));
+
+    // Stops the search if the output is closed or the hashes reported
+    // before the checkpoint complete the quantity.
+    let stop = StopToken::new();
+    let mut result = Ok(());
 
-                current_number += 1;
-            } else {
-                std::thread::sleep(std::time::Duration::from_millis(10));
+    finder.run_until(&stop, |found| {
+        let mut checkpoint = checkpoint.lock().unwrap();
+
+        // An unordered search may report more hashes after the stop,
+        // the resumed one may have reported hashes above its next number.
+        if result.is_err() || !checkpoint.accepts(found.number, config.hashes) {
+            return;
+        }
+
+        result = output.write_match(&found);
+        if result.is_ok() {
+            checkpoint.matches.push(found);
+
+            // Reported hashes are saved at once, so they aren't repeated
+            // after the resume.
+            if let Some(path) = &args.checkpoint {
+                save_checkpoint(&mut checkpoint, &progress, path);
             }
+        }
+
+        if result.is_err() || checkpoint.matches.len() >= config.hashes {
+            stop.stop();
+        }
+    });
+    drop(background);
+
+    let found = checkpoint.lock().unwrap().matches.len();
+    if found < config.hashes && result.is_ok() {
+        report_end(&config, found);
+    }
+
+    if let Some(path) = &args.checkpoint {
+        save_checkpoint(&mut checkpoint.lock().unwrap(), &progress, path);
+    }
+
+    finish(result.and_then(|_| output.finish()));
+}
+
+/// Reports the search which is over before the quantity of hashes,
+/// an unbounded search has reached its largest number.
+fn report_end(config: &SearchConfig, found: usize) {
+    match (config.end, config.last_number()) {
+        (None, Some(last)) => eprintln!(
+            "Found {found} of {} hashes, the search reached its largest number {last}",
+            config.hashes
+        ),
+        _ => eprintln!("Found {found} of {} hashes in the range", config.hashes),
+    }
+}
+
+/// Serves the search to the workers and prints the found hashes.
+fn serve(args: &Args, serve_args: &Serve, config: SearchConfig) {
+    if args.checkpoint.is_some() {
+        fail("--checkpoint isn't supported by serve");
+    }
+    if serve_args.lease == 0 {
+        fail("--lease should be positive");
+    }
+
+    let coordinator = Coordinator::bind(&serve_args.listen, config.clone())
+        .unwrap_or_else(|e| fail(&format!("can't listen on {}: {e}", serve_args.listen)))
+        .job_size(serve_args.job_size)
+        .lease(Duration::from_secs(serve_args.lease));
+
+    if let Ok(addr) = coordinator.local_addr() {
+        eprintln!("Listening on {addr}");
+    }
+
+    let background = Background::new(args, &coordinator.progress(), Goal::hashes(&config));
+
+    let mut output = Output::new(std::io::stdout().lock(), args.format, &config);
+    output.begin().unwrap_or_else(|e| fail(&e.to_string()));
 
-            if let Ok((number, hash)) = rx.try_recv() {
-                println!("{number}, {hash}");
-                complete_tasks += 1;
+    let stop = StopToken::new();
+    let mut found = 0;
+    let mut result = Ok(());
+
+    coordinator
+        .run_until(&stop, |m| {
+            if result.is_ok() {
+                result = output.write_match(&m);
+                found += 1;
+            }
+            if result.is_err() {
+                stop.stop();
             }
+        })
+        .unwrap_or_else(|e| fail(&e.to_string()));
+    drop(background);
+
+    if found < config.hashes && result.is_ok() {
+        report_end(&config, found);
+    }
+
+    finish(result.and_then(|_| output.finish()));
+}
+
+/// Processes the ranges of the coordinator until the search is over.
+fn work(worker: &Worker) {
+    match run_worker(&worker.connect, &StopToken::new()) {
+        Ok(jobs) => eprintln!("Completed {jobs} ranges"),
+        Err(e) => fail(&format!("connection to {} failed: {e}", worker.connect)),
+    }
+}
+
+/// Runs the submitted searches until the process is terminated.
+fn serve_api(api: &Api) {
+    let server = ApiServer::bind(&api.listen)
+        .unwrap_or_else(|e| fail(&format!("can't listen on {}: {e}", api.listen)));
+
+    if let Ok(addr) = server.local_addr() {
+        eprintln!("Listening on http://{addr}");
+    }
+
+    if let Err(e) = server.run_until(&StopToken::new()) {
+        fail(&e.to_string());
+    }
+}
+
+/// Counts the found hashes of the range and prints the totals.
+fn count(args: &Args, count_args: &Count, config: SearchConfig) {
+    match config.end {
+        None => fail("count requires --end"),
+        Some(end) if end < config.start => fail("--end is below --start"),
+        _ => {}
+    }
+
+    let finder = Finder::new(config);
+    let config = finder.config();
+    let progress = finder.progress();
+    let probability = config.predicate.probability(config.algorithm.output_size());
+
+    let background = Background::new(args, &progress, Goal::Numbers(config.numbers()));
+
+    let stop = StopToken::new();
+    let mut stdout = std::io::stdout().lock();
+    let mut result = Ok(());
+
+    let found = finder.count_until(&stop, |m| {
+        if !count_args.quiet && result.is_ok() {
+            result = writeln!(stdout, "{}, {}", m.number, m.hash);
+        }
+    });
+    drop(background);
+
+    let tried = progress.tried();
+    let density = match tried {
+        0 => 0.0,
+        tried => found as f64 / tried as f64,
+    };
+
+    // The ratio of no numbers is meaningless, it's skipped.
+    let result = result.and_then(|_| {
+        writeln!(stdout, "numbers: {tried}")?;
+        writeln!(stdout, "matches: {found}")?;
+        writeln!(stdout, "density: {density:.6e}")?;
+        writeln!(stdout, "expected: {probability:.6e} ({})", config.predicate)?;
+        match tried {
+            0 => Ok(()),
+            _ => writeln!(stdout, "ratio: {:.4}", density / probability),
         }
     });
+
+    finish(result);
 }
 
-/// Computing and checking hash of number for nulls at the end.
-/// In: number - target to hashing, 
-/// nulls - quantity of nulls at the end of hash, 
-/// tx - sends number and hash in successful case.
-fn process_hash(number: usize, nulls: usize, tx: Sender<(usize, String)>) {
-    let hash = sha256::digest(number.to_string());
+/// Measures the algorithms and prints the table of the results.
+fn run_bench(args: &Args, bench_args: &Bench) {
+    let predicate = if args.nulls.is_none()
+        && args.bits.is_none()
+        && args.prefix.is_none()
+        && args.suffix.is_none()
+        && args.target.is_none()
+        && args.nbits.is_none()
+    {
+        Predicate::zeros(5, Position::Suffix)
+    } else {
+        predicate(args).unwrap_or_else(|e| fail(&e))
+    };
+
+    let threads = if bench_args.threads.is_empty() {
+        let cpus = std::thread::available_parallelism().map_or(1, |n| n.get());
+        std::iter::successors(Some(1), |&t| Some(t * 2))
+            .take_while(|&t| t < cpus)
+            .chain([cpus])
+            .collect()
+    } else {
+        bench_args.threads.clone()
+    };
+    let chunk_sizes = if bench_args.chunk_size.is_empty() {
+        vec![1024, DEFAULT_CHUNK_SIZE, 16384]
+    } else {
+        bench_args.chunk_size.clone()
+    };
+
+    if threads.contains(&0) || chunk_sizes.contains(&0) {
+        fail("--threads and --chunk-size should be positive");
+    }
+
+    let algorithms = if bench_args.all_algorithms {
+        Algorithm::ALL.to_vec()
+    } else {
+        vec![args.algorithm]
+    };
+
+    println!(
+        "{:<10} {:>8} {:>8} {:>14}",
+        "algorithm", "threads", "chunk", "hashes/s"
+    );
+
+    let mut results: Vec<BenchResult> = Vec::new();
+
+    for algorithm in algorithms {
+        let config = SearchConfig::new(0, 0)
+            .predicate(predicate.clone())
+            .algorithm(algorithm)
+            .template(template(args));
+        let result =
+            bench(&config, &threads, &chunk_sizes, bench_args.numbers).unwrap_or_else(|e| fail(&e));
+
+        for run in &result.runs {
+            println!(
+                "{:<10} {:>8} {:>8} {:>14.0}",
+                algorithm.name(),
+                run.threads,
+                run.chunk_size,
+                run.hashes_per_second
+            );
+        }
+        results.push(result);
+    }
+
+    println!();
+    println!(
+        "{:<10} {:>18} {:>18} {:>18}",
+        "algorithm", "formatting ns", "hashing ns", "checking ns"
+    );
+
+    for result in &results {
+        let stages = &result.stages;
+        let share = |ns: f64| format!("{ns:.1} ({:.0}%)", ns / stages.total() * 100.0);
+
+        println!(
+            "{:<10} {:>18} {:>18} {:>18}",
+            result.algorithm.name(),
+            share(stages.formatting),
+            share(stages.hashing),
+            share(stages.checking)
+        );
+    }
 
-    if let Some(index) = hash.chars().rev().position(|i| i != '0') {
-        if index >= nulls {
-            _ = tx.send((number, hash));
+    if let Some(path) = &bench_args.json {
+        let json = serde_json::to_vec_pretty(&results).unwrap_or_default();
+        if let Err(e) = std::fs::write(path, json) {
+            fail(&format!("can't write {}: {e}", path.display()));
         }
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use crate::*;
+/// Checks the lines of the file or stdin, exits with 1 on any failed line.
+fn verify(args: &Args, verify_args: &Verify) {
+    let predicate = predicate(args).unwrap_or_else(|e| fail(&e));
+    let template = template(args);
+    let payload = Payload::new(
+        args.algorithm,
+        &template.prefix,
+        template.format,
+        &template.suffix,
+    );
+
+    let result = match &verify_args.file {
+        Some(path) => std::fs::File::open(path)
+            .map(std::io::BufReader::new)
+            .and_then(|reader| verify_lines(&payload, &predicate, reader))
+            .map_err(|e| format!("can't read {}: {e}", path.display())),
+        None => {
+            verify_lines(&payload, &predicate, std::io::stdin().lock()).map_err(|e| e.to_string())
+        }
+    };
+    let (checked, mismatches) = result.unwrap_or_else(|e| fail(&e));
 
-    #[test]
-    fn test_calculate_hash_value_1() {
-        let (tx, rx) = channel();
+    for m in &mismatches {
+        println!("line {}: {}: {}", m.line, m.text, m.reason);
+    }
+    eprintln!("Checked {checked} lines, {} failed", mismatches.len());
 
-        let number = 4163;
-        process_hash(number, 3, tx.clone());
+    if !mismatches.is_empty() {
+        std::process::exit(1);
+    }
+}
 
-        if let Ok(result) = rx.try_recv() {
-            assert_eq!(
-                (result.0, result.1.as_str()),
-                (
-                    number,
-                    "95d4362bd3cd4315d0bbe38dfa5d7fb8f0aed5f1a31d98d510907279194e3000"
-                )
-            );
-        } else {
-            panic!();
+/// Configures the pool of the worker threads and prints it to stderr.
+fn setup_pool(args: &Args) {
+    if args.threads == Some(0) {
+        fail("--threads should be positive");
+    }
+
+    let threads = configure_pool(args.threads, args.affinity.as_ref()).unwrap_or_else(|e| fail(&e));
+    let affinity = args
+        .affinity
+        .as_ref()
+        .map_or("none".to_string(), |cpus| cpus.to_string());
+
+    eprintln!("Threads: {threads}, affinity: {affinity}");
+}
+
+/// Predicate of the search from the nulls, the zero bits,
+/// the hex patterns or the target.
+fn predicate(args: &Args) -> Result<Predicate, String> {
+    let modes = [
+        args.nulls.is_some(),
+        args.bits.is_some(),
+        args.prefix.is_some() || args.suffix.is_some(),
+        args.target.is_some(),
+        args.nbits.is_some(),
+    ];
+
+    match modes.iter().filter(|&&m| m).count() {
+        0 => {
+            return Err(
+                "one of -N, --bits, --prefix, --suffix, --target or --nbits is required"
+                    .to_string(),
+            )
+        }
+        1 => {}
+        _ => {
+            return Err(
+                "-N, --bits, --prefix/--suffix, --target and --nbits can't be combined".to_string(),
+            )
         }
     }
 
-    #[test]
-    fn test_calculate_hash_value_2() {
-        let (tx, rx) = channel();
+    if let Some(nulls) = args.nulls {
+        Ok(Predicate::zeros(nulls as usize, args.position))
+    } else if let Some(bits) = args.bits {
+        Ok(Predicate::bits(bits as usize, args.position))
+    } else if let Some(target) = &args.target {
+        Predicate::target(target)
+    } else if let Some(nbits) = &args.nbits {
+        let bits = u32::from_str_radix(nbits.trim_start_matches("0x"), 16)
+            .map_err(|_| format!("invalid nbits {nbits}"))?;
+        Predicate::compact_target(bits)
+    } else {
+        Predicate::pattern(
+            args.prefix.as_deref().unwrap_or_default(),
+            args.suffix.as_deref().unwrap_or_default(),
+        )
+    }
+}
 
-        let number = 828028;
-        process_hash(number, 5, tx.clone());
+/// Background tasks of the search, they stop when it is dropped.
+struct Background {
+    done: Vec<Sender<()>>,
+}
 
-        if let Ok(result) = rx.try_recv() {
-            assert_eq!(
-                (result.0, result.1.as_str()),
-                (
-                    number,
-                    "d95f19b5269418c0d4479fa61b8e7696aa8df197082b431a65ff37595c100000"
-                )
-            );
-        } else {
-            panic!();
+impl Background {
+    /// Starts the report of the progress to stderr with --progress.
+    fn new(args: &Args, progress: &Progress, goal: Goal) -> Self {
+        let mut background = Self { done: Vec::new() };
+
+        if args.progress {
+            let progress = progress.clone();
+            background.spawn(move |done| report_progress(progress, goal, done));
         }
+        background
     }
 
-    #[test]
-    fn test_calculate_hash_quantity_1() {
+    /// Runs the task in a thread, the receiver of done is disconnected
+    /// when the tasks stop.
+    fn spawn<F: FnOnce(Receiver<()>) + Send + 'static>(&mut self, task: F) {
         let (tx, rx) = channel();
+        self.done.push(tx);
+        std::thread::spawn(move || task(rx));
+    }
+}
 
-        let sucessful_nums = vec![4163, 11848, 12843, 13467, 20215, 28892];
-        let sucessful_qnt = sucessful_nums.len();
+/// Fails on the error of the output, the closed output is no error,
+/// e.g. the pipe to head.
+fn finish(result: std::io::Result<()>) {
+    match result {
+        Err(e) if e.kind() != std::io::ErrorKind::BrokenPipe => fail(&e.to_string()),
+        _ => {}
+    }
+}
 
-        let unsucessful_nums = vec![1, 2, 3, 4, 5];
+/// Calls the task with the interval until the sender of done is dropped.
+fn periodically<F: FnMut()>(interval: Duration, done: Receiver<()>, mut task: F) {
+    while let Err(RecvTimeoutError::Timeout) = done.recv_timeout(interval) {
+        task();
+    }
+}
 
-        sucessful_nums
-            .into_iter()
-            .chain(unsucessful_nums.into_iter())
-            .for_each(|v| process_hash(v, 3, tx.clone()));
+/// End of the search for the estimation of the time left.
+#[derive(Clone, Copy)]
+enum Goal {
+    /// quantity of hashes, found with the probability per number
+    Hashes { hashes: usize, probability: f64 },
+    /// quantity of numbers, all of them are processed
+    Numbers(u64),
+}
 
-        let mut result_qnt = 0;
+impl Goal {
+    fn hashes(config: &SearchConfig) -> Self {
+        Goal::Hashes {
+            hashes: config.hashes,
+            probability: config.predicate.probability(config.algorithm.output_size()),
+        }
+    }
+}
 
-        while let Ok(_) = rx.try_recv() {
-            result_qnt += 1;
+/// Prints the progress of the search to stderr until the sender
+/// of done is dropped: quantity of tried numbers, current hashrate,
+/// quantity of found hashes and expected time to reach the goal.
+fn report_progress(progress: Progress, goal: Goal, done: Receiver<()>) {
+    let mut last_time = Instant::now();
+    let mut last_tried = 0;
+
+    periodically(PROGRESS_INTERVAL, done, || {
+        let now = Instant::now();
+        let tried = progress.tried();
+        let found = progress.found();
+
+        let rate = (tried - last_tried) as f64 / (now - last_time).as_secs_f64();
+
+        match goal {
+            Goal::Hashes { hashes, probability } => {
+                let eta = hashes.saturating_sub(found) as f64 / probability / rate;
+                eprintln!(
+                    "tried {tried}, {rate:.0} H/s, found {found}/{hashes}, ETA {}",
+                    format_eta(eta)
+                );
+            }
+            Goal::Numbers(numbers) => {
+                let eta = numbers.saturating_sub(tried) as f64 / rate;
+                eprintln!(
+                    "tried {tried}/{numbers}, {rate:.0} H/s, found {found}, ETA {}",
+                    format_eta(eta)
+                );
+            }
         }
 
-        assert_eq!(result_qnt, sucessful_qnt);
-    }
+        last_time = now;
+        last_tried = tried;
+    });
+}
 
-    #[test]
-    fn test_calculate_hash_quantity_2() {
-        let (tx, rx) = channel();
+/// Saves the frontier of the search and the reported hashes.
+/// The hashes below the frontier are reported before it is published,
+/// and the checkpoint is locked while the hashes are reported.
+fn save_checkpoint(checkpoint: &mut Checkpoint, progress: &Progress, path: &Path) {
+    checkpoint.advance(progress.next());
 
-        let sucessful_nums = vec![828028, 2513638, 3063274];
-        let sucessful_qnt = sucessful_nums.len();
+    if let Err(e) = checkpoint.save(path) {
+        eprintln!("Error: can't save checkpoint {}: {e}", path.display());
+    }
+}
 
-        let unsucessful_nums = vec![1, 2, 3, 4, 5];
+/// Formats seconds as hh:mm:ss.
+fn format_eta(seconds: f64) -> String {
+    if !seconds.is_finite() || seconds > u32::MAX as f64 {
+        return "unknown".to_string();
+    }
 
-        sucessful_nums
-            .into_iter()
-            .chain(unsucessful_nums.into_iter())
-            .for_each(|v| process_hash(v, 5, tx.clone()));
+    let seconds = seconds.round() as u64;
+    format!(
+        "{:02}:{:02}:{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
 
-        let mut result_qnt = 0;
+/// Data of the hex option, the 0x prefix is optional.
+fn hex_option(value: &Option<String>, name: &str) -> Vec<u8> {
+    match value {
+        Some(value) => hex::decode(value.trim_start_matches("0x"))
+            .unwrap_or_else(|_| fail(&format!("invalid hex {name} {value}"))),
+        None => Vec::new(),
+    }
+}
 
-        while let Ok(_) = rx.try_recv() {
-            result_qnt += 1;
+/// Template of the hashed data, the --header is hashed before it
+/// and the --trailer after it.
+fn template(args: &Args) -> Template {
+    let mut template = match &args.template {
+        Some(template) => template.parse().unwrap_or_else(|e: String| fail(&e)),
+        None => Template::default(),
+    };
+
+    if let Some(encoding) = args.encoding {
+        if template.format != NumberFormat::default() {
+            fail("--encoding conflicts with the format of the --template placeholder");
         }
+        template.format = NumberFormat::new(encoding, 0);
+    }
+
+    let max = template.format.encoding.max_number();
+    if args.start > max || args.end.is_some_and(|end| end > max) {
+        fail(&format!(
+            "the numbers of the search should be at most {max} for {}",
+            template.format.encoding
+        ));
+    }
 
-        assert_eq!(result_qnt, sucessful_qnt);
+    template
+        .prefix
+        .splice(0..0, hex_option(&args.header, "header"));
+    template.suffix.extend(hex_option(&args.trailer, "trailer"));
+    template
+}
+
+fn shard(args: &Args) -> Shard {
+    let shard = args.shard.unwrap_or_default();
+
+    match args.shard_block {
+        Some(0) => fail("--shard-block should be positive"),
+        Some(size) => shard.mode(ShardMode::Blocks(size)),
+        None => shard,
     }
 }
+
+fn fail(message: &str) -> ! {
+    eprintln!("Error: {message}");
+    std::process::exit(1);
+}
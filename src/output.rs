@@ -0,0 +1,275 @@
+use std::fmt;
+use std::io::{self, Write};
+use std::str::FromStr;
+use std::time::Instant;
+
+use serde::Serialize;
+
+use crate::finder::{Match, SearchConfig};
+
+/// Format of the found hashes output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Format {
+    /// "number, hash" lines
+    #[default]
+    Text,
+    /// single JSON document with the matches and the summary
+    Json,
+    /// JSON record per line, the last line is the summary
+    Ndjson,
+    /// comma separated values with the header line
+    Csv,
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Format::Text => "text",
+            Format::Json => "json",
+            Format::Ndjson => "ndjson",
+            Format::Csv => "csv",
+        })
+    }
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Format::Text),
+            "json" => Ok(Format::Json),
+            "ndjson" => Ok(Format::Ndjson),
+            "csv" => Ok(Format::Csv),
+            _ => Err(format!(
+                "unknown format {s}, expected one of: text, json, ndjson, csv"
+            )),
+        }
+    }
+}
+
+/// Found hash with the parameters of the search.
+#[derive(Debug, Serialize)]
+struct Record<'a> {
+    /// ordinal of the match starting from 1
+    index: usize,
+    number: u128,
+    hash: &'a str,
+    algorithm: &'a str,
+    difficulty: &'a str,
+    /// seconds from the start of the search to the discovery
+    elapsed: f64,
+}
+
+#[derive(Debug, Serialize)]
+struct Summary<'a> {
+    found: usize,
+    algorithm: &'a str,
+    difficulty: &'a str,
+    /// seconds from the start of the search
+    elapsed: f64,
+}
+
+/// Writes the found hashes in the format.
+/// Usage: begin, write_match for every found hash, finish.
+pub struct Output<W: Write> {
+    writer: W,
+    format: Format,
+    algorithm: String,
+    difficulty: String,
+    found: usize,
+    started: Instant,
+}
+
+impl<W: Write> Output<W> {
+    pub fn new(writer: W, format: Format, config: &SearchConfig) -> Self {
+        Self {
+            writer,
+            format,
+            algorithm: config.algorithm.to_string(),
+            difficulty: config.predicate.to_string(),
+            found: 0,
+            started: Instant::now(),
+        }
+    }
+
+    /// Writes the header of the output.
+    pub fn begin(&mut self) -> io::Result<()> {
+        match self.format {
+            Format::Json => writeln!(self.writer, "{{\"matches\":["),
+            Format::Csv => writeln!(
+                self.writer,
+                "index,number,hash,algorithm,difficulty,elapsed"
+            ),
+            Format::Text | Format::Ndjson => Ok(()),
+        }
+    }
+
+    pub fn write_match(&mut self, found: &Match) -> io::Result<()> {
+        self.found += 1;
+
+        let record = Record {
+            index: self.found,
+            number: found.number,
+            hash: &found.hash,
+            algorithm: &self.algorithm,
+            difficulty: &self.difficulty,
+            elapsed: found.elapsed.as_secs_f64(),
+        };
+
+        match self.format {
+            Format::Text => writeln!(self.writer, "{}, {}", found.number, found.hash),
+            Format::Json => {
+                if self.found > 1 {
+                    writeln!(self.writer, ",")?;
+                }
+                serde_json::to_writer(&mut self.writer, &record)?;
+                Ok(())
+            }
+            Format::Ndjson => {
+                serde_json::to_writer(&mut self.writer, &record)?;
+                writeln!(self.writer)
+            }
+            Format::Csv => writeln!(
+                self.writer,
+                "{},{},{},{},{},{}",
+                record.index,
+                record.number,
+                record.hash,
+                csv_field(record.algorithm),
+                csv_field(record.difficulty),
+                record.elapsed
+            ),
+        }
+    }
+
+    /// Writes the summary of the output.
+    pub fn finish(mut self) -> io::Result<()> {
+        let summary = Summary {
+            found: self.found,
+            algorithm: &self.algorithm,
+            difficulty: &self.difficulty,
+            elapsed: self.started.elapsed().as_secs_f64(),
+        };
+
+        match self.format {
+            Format::Json => {
+                if self.found > 0 {
+                    writeln!(self.writer)?;
+                }
+                write!(self.writer, "],\"summary\":")?;
+                serde_json::to_writer(&mut self.writer, &summary)?;
+                writeln!(self.writer, "}}")?;
+            }
+            Format::Ndjson => {
+                write!(self.writer, "{{\"summary\":")?;
+                serde_json::to_writer(&mut self.writer, &summary)?;
+                writeln!(self.writer, "}}")?;
+            }
+            Format::Text | Format::Csv => {}
+        }
+
+        self.writer.flush()
+    }
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn write(format: Format, matches: &[Match]) -> String {
+        let mut buffer = Vec::new();
+        let mut output = Output::new(&mut buffer, format, &SearchConfig::new(3, 2));
+
+        output.begin().unwrap();
+        for m in matches {
+            output.write_match(m).unwrap();
+        }
+        output.finish().unwrap();
+
+        String::from_utf8(buffer).unwrap()
+    }
+
+    fn matches() -> Vec<Match> {
+        vec![
+            Match {
+                number: 4163,
+                hash: "95d4362bd3cd4315d0bbe38dfa5d7fb8f0aed5f1a31d98d510907279194e3000"
+                    .to_string(),
+                elapsed: Duration::from_millis(500),
+            },
+            Match {
+                number: 11848,
+                hash: "cb58074fd7620cd0ff471922fd9df8812f29f302904b15e389fc14570a66f000"
+                    .to_string(),
+                elapsed: Duration::from_secs(1),
+            },
+        ]
+    }
+
+    #[test]
+    fn test_text() {
+        let text = write(Format::Text, &matches());
+
+        assert_eq!(
+            text,
+            "4163, 95d4362bd3cd4315d0bbe38dfa5d7fb8f0aed5f1a31d98d510907279194e3000\n\
+             11848, cb58074fd7620cd0ff471922fd9df8812f29f302904b15e389fc14570a66f000\n"
+        );
+    }
+
+    #[test]
+    fn test_json() {
+        for matches in [matches(), Vec::new()] {
+            let json = write(Format::Json, &matches);
+            let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+
+            assert_eq!(value["matches"].as_array().unwrap().len(), matches.len());
+            assert_eq!(value["summary"]["found"], matches.len());
+        }
+
+        let json = write(Format::Json, &matches());
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(value["matches"][1]["index"], 2);
+        assert_eq!(value["matches"][1]["number"], 11848);
+        assert_eq!(value["matches"][1]["algorithm"], "sha256");
+        assert_eq!(value["matches"][1]["difficulty"], "suffix=000");
+        assert_eq!(value["matches"][1]["elapsed"], 1.0);
+    }
+
+    #[test]
+    fn test_ndjson() {
+        let ndjson = write(Format::Ndjson, &matches());
+        let lines: Vec<serde_json::Value> = ndjson
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0]["number"], 4163);
+        assert_eq!(lines[2]["summary"]["found"], 2);
+    }
+
+    #[test]
+    fn test_csv() {
+        let csv = write(Format::Csv, &matches());
+        let lines: Vec<&str> = csv.lines().collect();
+
+        assert_eq!(lines[0], "index,number,hash,algorithm,difficulty,elapsed");
+        assert_eq!(
+            lines[1],
+            "1,4163,95d4362bd3cd4315d0bbe38dfa5d7fb8f0aed5f1a31d98d510907279194e3000,sha256,suffix=000,0.5"
+        );
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+    }
+}
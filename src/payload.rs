@@ -0,0 +1,151 @@
+use std::str::FromStr;
+
+use crate::encoding::{NumberFormat, MAX_ENCODED_LEN};
+use crate::hasher::{Algorithm, Midstate, MAX_OUTPUT_SIZE};
+
+/// Hashed data around the number, e.g. user=alice;nonce={n};ts=1700000000.
+/// The placeholder {n} may have a format specification: {n:08}, {n:x},
+/// {n:016x}, {n:b36}. Other braces are a part of the data.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Template {
+    /// data before the number
+    pub prefix: Vec<u8>,
+    pub format: NumberFormat,
+    /// data after the number
+    pub suffix: Vec<u8>,
+}
+
+impl FromStr for Template {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut placeholders = s.match_indices("{n").filter_map(|(start, _)| {
+            let rest = &s[start + 2..];
+            let end = rest.find('}')?;
+
+            match rest[..end].strip_prefix(':') {
+                Some(spec) => Some((start, start + 2 + end + 1, spec)),
+                None if end == 0 => Some((start, start + 3, "")),
+                None => None,
+            }
+        });
+
+        let (start, end, spec) = placeholders
+            .next()
+            .ok_or_else(|| format!("template {s} has no {{n}} placeholder"))?;
+        if placeholders.next().is_some() {
+            return Err(format!("template {s} has several {{n}} placeholders"));
+        }
+
+        Ok(Self {
+            prefix: s.as_bytes()[..start].to_vec(),
+            format: spec.parse()?,
+            suffix: s.as_bytes()[end..].to_vec(),
+        })
+    }
+}
+
+/// Hashing of the numbers: the digest of the header, the number
+/// in its format and the trailer. The header is hashed once.
+#[derive(Debug, Clone)]
+pub struct Payload {
+    hasher: Midstate,
+    format: NumberFormat,
+    trailer: Vec<u8>,
+}
+
+impl Payload {
+    pub fn new(algorithm: Algorithm, header: &[u8], format: NumberFormat, trailer: &[u8]) -> Self {
+        Self {
+            hasher: Midstate::new(algorithm, header),
+            format,
+            trailer: trailer.to_vec(),
+        }
+    }
+
+    pub fn algorithm(&self) -> Algorithm {
+        self.hasher.algorithm()
+    }
+
+    pub fn format(&self) -> NumberFormat {
+        self.format
+    }
+
+    pub fn trailer(&self) -> &[u8] {
+        &self.trailer
+    }
+
+    /// Writes the raw bytes of the digest of the number to the start
+    /// of the buffer, the number is encoded on the stack.
+    /// Out: size of the digest.
+    pub fn digest_to(&self, number: u128, buffer: &mut [u8; MAX_OUTPUT_SIZE]) -> usize {
+        let mut text = [0; MAX_ENCODED_LEN];
+        let text = self.format.write(number, &mut text);
+
+        self.digest_encoded_to(text, buffer)
+    }
+
+    /// Writes the raw bytes of the digest of the number already encoded
+    /// in the format to the start of the buffer.
+    /// Out: size of the digest.
+    pub fn digest_encoded_to(&self, encoded: &[u8], buffer: &mut [u8; MAX_OUTPUT_SIZE]) -> usize {
+        self.hasher.digest_parts_to(&[encoded, &self.trailer], buffer)
+    }
+
+    /// Raw bytes of the digest of the number.
+    pub fn digest(&self, number: u128) -> Vec<u8> {
+        let mut buffer = [0; MAX_OUTPUT_SIZE];
+        let size = self.digest_to(number, &mut buffer);
+        buffer[..size].to_vec()
+    }
+}
+
+impl From<Algorithm> for Payload {
+    /// Decimal numbers without the header and the trailer.
+    fn from(algorithm: Algorithm) -> Self {
+        Self::new(algorithm, &[], NumberFormat::default(), &[])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encoding::Encoding;
+
+    #[test]
+    fn test_template() {
+        let template: Template = "user=alice;nonce={n};ts=1700000000".parse().unwrap();
+
+        assert_eq!(template.prefix, b"user=alice;nonce=");
+        assert_eq!(template.format, NumberFormat::default());
+        assert_eq!(template.suffix, b";ts=1700000000");
+
+        let template: Template = r#"{"nonce":"{n:016x}"}"#.parse().unwrap();
+        assert_eq!(template.prefix, br#"{"nonce":""#);
+        assert_eq!(template.format, NumberFormat::new(Encoding::Hex, 16));
+        assert_eq!(template.suffix, br#""}"#);
+
+        assert!("nonce={m}".parse::<Template>().is_err());
+        assert!("{n}{n}".parse::<Template>().is_err());
+        assert!("{n:y}".parse::<Template>().is_err());
+    }
+
+    #[test]
+    fn test_payload() {
+        let payload = Payload::new(
+            Algorithm::Sha256,
+            b"user=alice;nonce=",
+            NumberFormat::new(Encoding::Base36, 4),
+            b";ts=1700000000",
+        );
+
+        assert_eq!(
+            hex::encode(payload.digest(46655)),
+            sha256::digest("user=alice;nonce=0zzz;ts=1700000000")
+        );
+        assert_eq!(
+            hex::encode(Payload::from(Algorithm::Sha256).digest(4163)),
+            sha256::digest("4163")
+        );
+    }
+}
@@ -0,0 +1,157 @@
+use std::fmt;
+use std::io;
+use std::str::FromStr;
+
+/// Set of CPUs in the Linux cpulist form, e.g. 0-3,6.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CpuList(Vec<usize>);
+
+impl CpuList {
+    /// In: cpus - indexes of the CPUs, at least one.
+    pub fn new(mut cpus: Vec<usize>) -> Result<Self, String> {
+        cpus.sort_unstable();
+        cpus.dedup();
+
+        if cpus.is_empty() {
+            return Err("empty CPU list".to_string());
+        }
+        Ok(Self(cpus))
+    }
+
+    /// Indexes of the CPUs in ascending order.
+    pub fn cpus(&self) -> &[usize] {
+        &self.0
+    }
+}
+
+impl fmt::Display for CpuList {
+    /// Consecutive CPUs are joined in ranges.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = Vec::new();
+        let mut i = 0;
+
+        while i < self.0.len() {
+            let first = self.0[i];
+            while i + 1 < self.0.len() && self.0[i + 1] == self.0[i] + 1 {
+                i += 1;
+            }
+
+            if self.0[i] == first {
+                parts.push(first.to_string());
+            } else {
+                parts.push(format!("{first}-{}", self.0[i]));
+            }
+            i += 1;
+        }
+
+        f.write_str(&parts.join(","))
+    }
+}
+
+impl FromStr for CpuList {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid CPU list {s}, expected e.g. 0-3,6");
+        let mut cpus = Vec::new();
+
+        for part in s.split(',') {
+            let cpu = |n: &str| n.trim().parse::<usize>().map_err(|_| invalid());
+
+            match part.split_once('-') {
+                Some((first, last)) => {
+                    let (first, last) = (cpu(first)?, cpu(last)?);
+                    if first > last {
+                        return Err(invalid());
+                    }
+                    cpus.extend(first..=last);
+                }
+                None => cpus.push(cpu(part)?),
+            }
+        }
+
+        CpuList::new(cpus)
+    }
+}
+
+/// Configures the global rayon pool of the search workers.
+/// In: threads - quantity of threads, by default the quantity of CPUs
+/// of the affinity or of the system,
+/// affinity - CPUs to pin the threads to, round-robin.
+/// Out: quantity of threads of the pool.
+pub fn configure_pool(threads: Option<usize>, affinity: Option<&CpuList>) -> Result<usize, String> {
+    let threads = threads.or(affinity.map(|cpus| cpus.cpus().len()));
+
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(threads.unwrap_or(0))
+        .build_global()
+        .map_err(|e| format!("can't build the thread pool: {e}"))?;
+
+    if let Some(affinity) = affinity {
+        let cpus = affinity.cpus();
+
+        rayon::broadcast(|context| pin_thread(cpus[context.index() % cpus.len()]))
+            .into_iter()
+            .collect::<io::Result<()>>()
+            .map_err(|e| format!("can't set the CPU affinity {affinity}: {e}"))?;
+    }
+
+    Ok(rayon::current_num_threads())
+}
+
+/// Pins the current thread to the CPU.
+#[cfg(target_os = "linux")]
+pub fn pin_thread(cpu: usize) -> io::Result<()> {
+    if cpu >= libc::CPU_SETSIZE as usize {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("CPU {cpu} is out of range"),
+        ));
+    }
+
+    // SAFETY: the set is a plain bit mask, the CPU is inside of it.
+    let result = unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        libc::CPU_SET(cpu, &mut set);
+        libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set)
+    };
+
+    if result == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+/// Pins the current thread to the CPU.
+#[cfg(not(target_os = "linux"))]
+pub fn pin_thread(_cpu: usize) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "CPU affinity is supported on Linux only",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cpu_list() {
+        let list: CpuList = "6,0-3,2".parse().unwrap();
+
+        assert_eq!(list.cpus(), &[0, 1, 2, 3, 6]);
+        assert_eq!(list.to_string(), "0-3,6");
+        assert_eq!("5".parse::<CpuList>().unwrap().to_string(), "5");
+
+        assert!("".parse::<CpuList>().is_err());
+        assert!("3-1".parse::<CpuList>().is_err());
+        assert!("a".parse::<CpuList>().is_err());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_pin_thread() {
+        assert!(pin_thread(usize::MAX).is_err());
+    }
+}
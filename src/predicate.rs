@@ -0,0 +1,374 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+/// Position of the nulls in the character representation of the hash.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Position {
+    Prefix,
+    #[default]
+    Suffix,
+    Both,
+}
+
+impl FromStr for Position {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "prefix" => Ok(Position::Prefix),
+            "suffix" => Ok(Position::Suffix),
+            "both" => Ok(Position::Both),
+            _ => Err(format!(
+                "unknown position {s}, expected one of: prefix, suffix, both"
+            )),
+        }
+    }
+}
+
+/// Condition which the hash of a number should satisfy.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Predicate {
+    /// Character representation of the hash starts with the prefix
+    /// and ends with the suffix. Patterns are stored as hex digits.
+    Pattern { prefix: Vec<u8>, suffix: Vec<u8> },
+    /// Raw bytes of the hash contain the quantity of zero bits
+    /// at the position.
+    Bits { bits: usize, position: Position },
+    /// Hash interpreted as a big-endian integer is below the target.
+    /// Target is stored as big-endian bytes.
+    Target { target: Vec<u8> },
+}
+
+impl Predicate {
+    /// Hash contains the quantity of nulls at the position.
+    pub fn zeros(nulls: usize, position: Position) -> Self {
+        let zeros = vec![0; nulls];
+
+        match position {
+            Position::Prefix => Predicate::Pattern {
+                prefix: zeros,
+                suffix: Vec::new(),
+            },
+            Position::Suffix => Predicate::Pattern {
+                prefix: Vec::new(),
+                suffix: zeros,
+            },
+            Position::Both => Predicate::Pattern {
+                prefix: zeros.clone(),
+                suffix: zeros,
+            },
+        }
+    }
+
+    /// Hash starts with the prefix and ends with the suffix.
+    /// In: prefix, suffix - hex patterns, may be empty.
+    pub fn pattern(prefix: &str, suffix: &str) -> Result<Self, String> {
+        Ok(Predicate::Pattern {
+            prefix: parse_hex_digits(prefix)?,
+            suffix: parse_hex_digits(suffix)?,
+        })
+    }
+
+    /// Hash contains the quantity of zero bits at the position.
+    pub fn bits(bits: usize, position: Position) -> Self {
+        Predicate::Bits { bits, position }
+    }
+
+    /// Hash is below the target.
+    /// In: target - big-endian integer in hex.
+    pub fn target(target: &str) -> Result<Self, String> {
+        let target = target.trim_start_matches("0x");
+        let target = if target.len() % 2 == 1 {
+            format!("0{target}")
+        } else {
+            target.to_string()
+        };
+
+        hex::decode(&target)
+            .map(|target| Predicate::Target { target })
+            .map_err(|_| format!("invalid hex target {target}"))
+    }
+
+    /// Hash is below the target in the compact nBits encoding,
+    /// e.g. 0x1d00ffff: the highest byte is the size of the target
+    /// in bytes and the lower bytes are the highest bytes of the target.
+    pub fn compact_target(bits: u32) -> Result<Self, String> {
+        let size = (bits >> 24) as usize;
+        let mantissa = bits & 0x007f_ffff;
+
+        if bits & 0x0080_0000 != 0 {
+            return Err(format!("negative compact target {bits:#010x}"));
+        }
+        if size > 32 {
+            return Err(format!("compact target {bits:#010x} overflows 256 bits"));
+        }
+
+        let target = if size <= 3 {
+            (mantissa >> (8 * (3 - size))).to_be_bytes()[4 - size..].to_vec()
+        } else {
+            let mut target = mantissa.to_be_bytes()[1..].to_vec();
+            target.resize(size, 0);
+            target
+        };
+
+        Ok(Predicate::Target { target })
+    }
+
+    /// Probability of a random hash to satisfy the predicate.
+    /// In: output_size - size of the hash in bytes.
+    pub fn probability(&self, output_size: usize) -> f64 {
+        match self {
+            Predicate::Pattern { prefix, suffix } => {
+                16f64.powi(-((prefix.len() + suffix.len()).min(output_size * 2) as i32))
+            }
+            Predicate::Bits { bits, position } => {
+                let bits = match position {
+                    Position::Prefix | Position::Suffix => *bits,
+                    Position::Both => *bits * 2,
+                };
+                2f64.powi(-(bits.min(output_size * 8) as i32))
+            }
+            Predicate::Target { target } => {
+                let target = target.iter().fold(0f64, |n, &b| n * 256.0 + b as f64);
+                (target / 256f64.powi(output_size as i32)).min(1.0)
+            }
+        }
+    }
+
+    /// Checks the raw bytes of the hash.
+    pub fn matches(&self, digest: &[u8]) -> bool {
+        match self {
+            Predicate::Pattern { prefix, suffix } => {
+                let digits = digest.len() * 2;
+
+                prefix.len() <= digits
+                    && suffix.len() <= digits
+                    && prefix
+                        .iter()
+                        .enumerate()
+                        .all(|(i, &d)| hex_digit(digest, i) == d)
+                    && suffix
+                        .iter()
+                        .enumerate()
+                        .all(|(i, &d)| hex_digit(digest, digits - suffix.len() + i) == d)
+            }
+            Predicate::Bits { bits, position } => match position {
+                Position::Prefix => leading_zero_bits(digest) >= *bits,
+                Position::Suffix => trailing_zero_bits(digest) >= *bits,
+                Position::Both => {
+                    leading_zero_bits(digest) >= *bits && trailing_zero_bits(digest) >= *bits
+                }
+            },
+            Predicate::Target { target } => less_than(digest, target),
+        }
+    }
+}
+
+impl Default for Predicate {
+    fn default() -> Self {
+        Predicate::zeros(0, Position::Suffix)
+    }
+}
+
+impl fmt::Display for Predicate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Predicate::Pattern { prefix, suffix } => {
+                let to_hex = |digits: &[u8]| -> String {
+                    digits
+                        .iter()
+                        .map(|&d| char::from_digit(d as u32, 16).unwrap_or('?'))
+                        .collect()
+                };
+
+                let mut parts = Vec::new();
+                if !prefix.is_empty() {
+                    parts.push(format!("prefix={}", to_hex(prefix)));
+                }
+                if !suffix.is_empty() {
+                    parts.push(format!("suffix={}", to_hex(suffix)));
+                }
+
+                if parts.is_empty() {
+                    f.write_str("any")
+                } else {
+                    f.write_str(&parts.join(" "))
+                }
+            }
+            Predicate::Bits { bits, position } => {
+                let position = match position {
+                    Position::Prefix => "leading",
+                    Position::Suffix => "trailing",
+                    Position::Both => "leading and trailing",
+                };
+                write!(f, "{bits} {position} zero bits")
+            }
+            Predicate::Target { target } => write!(f, "target={}", hex::encode(target)),
+        }
+    }
+}
+
+/// Hex digit of the hash by its index in the character representation.
+fn hex_digit(digest: &[u8], index: usize) -> u8 {
+    let byte = digest[index / 2];
+    if index.is_multiple_of(2) {
+        byte >> 4
+    } else {
+        byte & 0x0f
+    }
+}
+
+fn leading_zero_bits(digest: &[u8]) -> usize {
+    match digest.iter().position(|&b| b != 0) {
+        Some(index) => index * 8 + digest[index].leading_zeros() as usize,
+        None => digest.len() * 8,
+    }
+}
+
+fn trailing_zero_bits(digest: &[u8]) -> usize {
+    match digest.iter().rev().position(|&b| b != 0) {
+        Some(index) => index * 8 + digest[digest.len() - 1 - index].trailing_zeros() as usize,
+        None => digest.len() * 8,
+    }
+}
+
+/// Compares big-endian integers of any size.
+fn less_than(a: &[u8], b: &[u8]) -> bool {
+    let strip = |n: &[u8]| -> usize { n.iter().position(|&b| b != 0).unwrap_or(n.len()) };
+
+    let a = &a[strip(a)..];
+    let b = &b[strip(b)..];
+
+    (a.len(), a) < (b.len(), b)
+}
+
+fn parse_hex_digits(pattern: &str) -> Result<Vec<u8>, String> {
+    pattern
+        .chars()
+        .map(|c| {
+            c.to_digit(16)
+                .map(|d| d as u8)
+                .ok_or_else(|| format!("invalid hex pattern {pattern}"))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn digest(hash: &str) -> Vec<u8> {
+        hex::decode(hash).unwrap()
+    }
+
+    #[test]
+    fn test_zeros_position() {
+        let hash = digest("000f1234567890abcdef0001");
+
+        assert!(Predicate::zeros(3, Position::Prefix).matches(&hash));
+        assert!(!Predicate::zeros(4, Position::Prefix).matches(&hash));
+        assert!(Predicate::zeros(0, Position::Suffix).matches(&hash));
+        assert!(!Predicate::zeros(1, Position::Suffix).matches(&hash));
+
+        let hash = digest("000f1234567890abcdef0000");
+
+        assert!(Predicate::zeros(4, Position::Suffix).matches(&hash));
+        assert!(Predicate::zeros(3, Position::Both).matches(&hash));
+        assert!(!Predicate::zeros(4, Position::Both).matches(&hash));
+    }
+
+    #[test]
+    fn test_pattern() {
+        let hash = digest("cafe1234567890abcdefbabe");
+
+        assert!(Predicate::pattern("caf", "abe").unwrap().matches(&hash));
+        assert!(Predicate::pattern("CAFE", "").unwrap().matches(&hash));
+        assert!(!Predicate::pattern("", "cafe").unwrap().matches(&hash));
+        assert!(!Predicate::pattern(&"c".repeat(25), "")
+            .unwrap()
+            .matches(&hash));
+        assert!(Predicate::pattern("xyz", "").is_err());
+    }
+
+    #[test]
+    fn test_bits() {
+        // 0x1c = 0b00011100, 0x30 = 0b00110000
+        let hash = digest("001c123430");
+
+        assert!(Predicate::bits(11, Position::Prefix).matches(&hash));
+        assert!(!Predicate::bits(12, Position::Prefix).matches(&hash));
+        assert!(Predicate::bits(4, Position::Suffix).matches(&hash));
+        assert!(!Predicate::bits(5, Position::Suffix).matches(&hash));
+        assert!(Predicate::bits(4, Position::Both).matches(&hash));
+        assert!(!Predicate::bits(5, Position::Both).matches(&hash));
+
+        let hash = digest("0000");
+
+        assert!(Predicate::bits(16, Position::Both).matches(&hash));
+        assert!(!Predicate::bits(17, Position::Prefix).matches(&hash));
+    }
+
+    #[test]
+    fn test_target() {
+        let target = Predicate::target("00ff").unwrap();
+
+        assert!(target.matches(&digest("00fe")));
+        assert!(target.matches(&digest("000000fe")));
+        assert!(!target.matches(&digest("00ff")));
+        assert!(!target.matches(&digest("0100")));
+        assert!(!target.matches(&digest("000100")));
+
+        assert_eq!(
+            Predicate::target("0x1ff").unwrap(),
+            Predicate::Target {
+                target: vec![0x01, 0xff]
+            }
+        );
+        assert!(Predicate::target("xyz").is_err());
+    }
+
+    #[test]
+    fn test_probability() {
+        assert_eq!(
+            Predicate::zeros(5, Position::Suffix).probability(32),
+            16f64.powi(-5)
+        );
+        assert_eq!(
+            Predicate::zeros(2, Position::Both).probability(32),
+            16f64.powi(-4)
+        );
+        assert_eq!(
+            Predicate::bits(18, Position::Prefix).probability(32),
+            2f64.powi(-18)
+        );
+        assert_eq!(Predicate::target("01").unwrap().probability(1), 1.0 / 256.0);
+        assert_eq!(Predicate::target("ffff").unwrap().probability(1), 1.0);
+    }
+
+    #[test]
+    fn test_compact_target() {
+        let mut genesis = vec![0x00, 0xff, 0xff];
+        genesis.resize(0x1d, 0);
+
+        assert_eq!(
+            Predicate::compact_target(0x1d00ffff).unwrap(),
+            Predicate::Target { target: genesis }
+        );
+        assert_eq!(
+            Predicate::compact_target(0x03123456).unwrap(),
+            Predicate::Target {
+                target: vec![0x12, 0x34, 0x56]
+            }
+        );
+        assert_eq!(
+            Predicate::compact_target(0x01123456).unwrap(),
+            Predicate::Target { target: vec![0x12] }
+        );
+        assert!(Predicate::compact_target(0x04923456).is_err());
+        assert!(Predicate::compact_target(0x21010000).is_err());
+    }
+}
@@ -0,0 +1,62 @@
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Shared counters of the search progress.
+/// Clones of the progress refer to the same counters.
+#[derive(Debug, Clone)]
+pub struct Progress {
+    tried: Arc<AtomicU64>,
+    found: Arc<AtomicUsize>,
+    /// 128-bit numbers have no atomics, the lock is taken once per range
+    next: Arc<Mutex<Option<u128>>>,
+}
+
+impl Default for Progress {
+    fn default() -> Self {
+        Self {
+            tried: Arc::default(),
+            found: Arc::default(),
+            next: Arc::new(Mutex::new(Some(0))),
+        }
+    }
+}
+
+impl Progress {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Quantity of numbers checked by the workers.
+    pub fn tried(&self) -> u64 {
+        self.tried.load(Ordering::Relaxed)
+    }
+
+    /// Quantity of hashes reported by the search.
+    pub fn found(&self) -> usize {
+        self.found.load(Ordering::Relaxed)
+    }
+
+    /// All numbers below the returned one are processed and their
+    /// hashes are reported, none once all numbers are processed.
+    pub fn next(&self) -> Option<u128> {
+        *self.next.lock().unwrap()
+    }
+
+    pub(crate) fn add_tried(&self, quantity: u64) {
+        self.tried.fetch_add(quantity, Ordering::Relaxed);
+    }
+
+    pub(crate) fn add_found(&self) {
+        self.found.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn set_next(&self, next: Option<u128>) {
+        *self.next.lock().unwrap() = next;
+    }
+
+    pub(crate) fn reset(&self, start: Option<u128>) {
+        self.tried.store(0, Ordering::Relaxed);
+        self.found.store(0, Ordering::Relaxed);
+        *self.next.lock().unwrap() = start;
+    }
+}
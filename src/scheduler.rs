@@ -0,0 +1,284 @@
+use std::collections::BTreeMap;
+use std::ops::Range;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::shard::{Shard, ShardMode};
+
+/// Numbers of the search: start, start + step, start + 2 * step, ...
+/// up to the end, only those of them which belong to the shard.
+/// The numbers are addressed by their ascending indexes 0, 1, 2, ...
+/// The numbers are 128-bit, the indexes of one search are usize.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Sequence {
+    start: u128,
+    end: Option<u128>,
+    step: usize,
+    shard: Shard,
+}
+
+impl Sequence {
+    pub(crate) fn new(start: u128, end: Option<u128>, step: usize, shard: Shard) -> Self {
+        Self {
+            start,
+            end,
+            step: step.max(1),
+            shard,
+        }
+    }
+
+    /// Number by its index, none if it is beyond the end
+    /// or above u128::MAX.
+    pub(crate) fn number(&self, index: usize) -> Option<u128> {
+        let count = self.shard.count;
+        let shard = self.shard.index;
+
+        let position = match self.shard.mode {
+            ShardMode::Interleaved => index.checked_mul(count)?.checked_add(shard)?,
+            ShardMode::Blocks(size) => {
+                let size = size.max(1);
+                let block = (index / size).checked_mul(count)?.checked_add(shard)?;
+                block.checked_mul(size)?.checked_add(index % size)?
+            }
+        };
+
+        let number = (position as u128)
+            .checked_mul(self.step as u128)?
+            .checked_add(self.start)?;
+
+        match self.end {
+            Some(end) if number > end => None,
+            _ => Some(number),
+        }
+    }
+
+    /// Index of the first number which isn't below the number,
+    /// usize::MAX if the index is above it.
+    pub(crate) fn index_of(&self, number: u128) -> usize {
+        if number <= self.start {
+            return 0;
+        }
+
+        let count = self.shard.count;
+        let shard = self.shard.index;
+        let Ok(position) = usize::try_from((number - self.start).div_ceil(self.step as u128))
+        else {
+            return usize::MAX;
+        };
+
+        match self.shard.mode {
+            ShardMode::Interleaved => position.saturating_sub(shard).div_ceil(count),
+            ShardMode::Blocks(size) => {
+                let size = size.max(1);
+                let block = position / size;
+
+                if block >= shard && (block - shard).is_multiple_of(count) {
+                    (block - shard) / count * size + position % size
+                } else {
+                    block
+                        .saturating_sub(shard)
+                        .div_ceil(count)
+                        .saturating_mul(size)
+                }
+            }
+        }
+    }
+
+    /// Index of the number, none if it isn't a number of the sequence.
+    pub(crate) fn position(&self, number: u128) -> Option<usize> {
+        let index = self.index_of(number);
+        (self.number(index) == Some(number)).then_some(index)
+    }
+
+    /// Indexes of the numbers up to the end, or up to u128::MAX
+    /// without the end.
+    pub(crate) fn limit(&self) -> usize {
+        let end = self.end.unwrap_or(u128::MAX);
+        let index = self.index_of(end);
+
+        match self.number(index) {
+            Some(number) if number == end => index.saturating_add(1),
+            _ => index,
+        }
+    }
+}
+
+/// Hands out contiguous ranges of indexes of the numbers to the workers.
+#[derive(Debug)]
+pub(crate) struct Chunks {
+    next: AtomicUsize,
+    size: usize,
+    /// the ranges end before the limit
+    limit: usize,
+}
+
+impl Chunks {
+    /// In: start - first index to hand out,
+    /// limit - the indexes are below it,
+    /// size - quantity of indexes in one range.
+    pub(crate) fn new(start: usize, limit: usize, size: usize) -> Self {
+        Self {
+            next: AtomicUsize::new(start),
+            size: size.max(1),
+            limit,
+        }
+    }
+
+    /// Claims the next range of indexes, the ranges are over
+    /// when the limit is reached.
+    pub(crate) fn claim(&self) -> Option<Range<usize>> {
+        let start = self.next.fetch_add(self.size, Ordering::Relaxed);
+
+        if start < self.limit {
+            Some(start..start.saturating_add(self.size).min(self.limit))
+        } else {
+            None
+        }
+    }
+}
+
+/// Tracks the completed ranges of indexes, which are reported
+/// by the workers in any order.
+#[derive(Debug)]
+pub(crate) struct Frontier {
+    next: usize,
+    done: BTreeMap<usize, usize>,
+}
+
+impl Frontier {
+    /// In: start - first index of the search.
+    pub(crate) fn new(start: usize) -> Self {
+        Self {
+            next: start,
+            done: BTreeMap::new(),
+        }
+    }
+
+    /// Marks the range as processed.
+    pub(crate) fn complete(&mut self, range: Range<usize>) {
+        self.done.insert(range.start, range.end);
+
+        while let Some(end) = self.done.remove(&self.next) {
+            self.next = end;
+        }
+    }
+
+    /// All indexes below the returned one are processed.
+    pub(crate) fn next(&self) -> usize {
+        self.next
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn numbers(sequence: &Sequence) -> Vec<u128> {
+        (0..sequence.limit().min(100))
+            .map(|i| sequence.number(i).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_sequence_step() {
+        let sequence = Sequence::new(5, Some(20), 3, Shard::default());
+
+        assert_eq!(numbers(&sequence), vec![5, 8, 11, 14, 17, 20]);
+        assert_eq!(sequence.number(6), None);
+        assert_eq!(sequence.index_of(9), 2);
+        assert_eq!(sequence.index_of(11), 2);
+    }
+
+    #[test]
+    fn test_sequence_shards() {
+        for mode in [ShardMode::Interleaved, ShardMode::Blocks(3)] {
+            let mut all: Vec<u128> = (0..4)
+                .flat_map(|i| {
+                    let shard = Shard::new(i, 4).unwrap().mode(mode);
+                    numbers(&Sequence::new(1, Some(50), 2, shard))
+                })
+                .collect();
+            all.sort();
+
+            assert_eq!(all, (1..=50).step_by(2).collect::<Vec<_>>());
+        }
+
+        let shard = Shard::new(1, 3).unwrap().mode(ShardMode::Blocks(2));
+        let sequence = Sequence::new(0, Some(20), 1, shard);
+
+        assert_eq!(numbers(&sequence), vec![2, 3, 8, 9, 14, 15, 20]);
+    }
+
+    #[test]
+    fn test_sequence_index_of() {
+        for mode in [ShardMode::Interleaved, ShardMode::Blocks(3)] {
+            let shard = Shard::new(2, 3).unwrap().mode(mode);
+            let sequence = Sequence::new(10, None, 7, shard);
+
+            for number in 0..300 {
+                let index = sequence.index_of(number);
+
+                assert!(sequence.number(index).unwrap() >= number);
+                assert!(index == 0 || sequence.number(index - 1).unwrap() < number);
+            }
+        }
+    }
+
+    #[test]
+    fn test_sequence_position() {
+        let shard = Shard::new(2, 3).unwrap().mode(ShardMode::Blocks(2));
+        let sequence = Sequence::new(10, Some(200), 7, shard);
+
+        for number in 0..300 {
+            let expected = (0..sequence.limit()).find(|&i| sequence.number(i) == Some(number));
+            assert_eq!(sequence.position(number), expected);
+        }
+        assert_eq!(sequence.position(1 << 100), None);
+    }
+
+    #[test]
+    fn test_sequence_u128() {
+        let start = u128::MAX - 10;
+        let sequence = Sequence::new(start, None, 4, Shard::default());
+
+        assert_eq!(numbers(&sequence), vec![start, start + 4, start + 8]);
+        assert_eq!(sequence.number(3), None);
+        assert_eq!(sequence.index_of(u128::MAX), 3);
+
+        let sequence = Sequence::new(0, None, 1, Shard::default());
+        assert_eq!(sequence.index_of(1 << 100), usize::MAX);
+        assert_eq!(sequence.number(usize::MAX), Some(usize::MAX as u128));
+    }
+
+    #[test]
+    fn test_chunks_claim() {
+        let chunks = Chunks::new(1, usize::MAX, 10);
+
+        assert_eq!(chunks.claim(), Some(1..11));
+        assert_eq!(chunks.claim(), Some(11..21));
+    }
+
+    #[test]
+    fn test_chunks_claim_limit() {
+        let chunks = Chunks::new(5, 17, 5);
+
+        assert_eq!(chunks.claim(), Some(5..10));
+        assert_eq!(chunks.claim(), Some(10..15));
+        assert_eq!(chunks.claim(), Some(15..17));
+        assert_eq!(chunks.claim(), None);
+        assert_eq!(chunks.claim(), None);
+    }
+
+    #[test]
+    fn test_frontier_complete() {
+        let mut frontier = Frontier::new(1);
+
+        frontier.complete(11..21);
+        assert_eq!(frontier.next(), 1);
+
+        frontier.complete(21..31);
+        assert_eq!(frontier.next(), 1);
+
+        frontier.complete(1..11);
+        assert_eq!(frontier.next(), 31);
+    }
+}
@@ -0,0 +1,104 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+/// Subset of the numbers processed by one of several processes.
+/// Shards of the same search don't intersect and together they cover
+/// all numbers of the search.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Shard {
+    /// index of the shard starting from 0
+    pub index: usize,
+    /// quantity of shards
+    pub count: usize,
+    pub mode: ShardMode,
+}
+
+/// Distribution of the numbers between the shards.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ShardMode {
+    /// number k of the search belongs to shard k % count
+    #[default]
+    Interleaved,
+    /// numbers are split in blocks of the size,
+    /// block k of the search belongs to shard k % count
+    Blocks(usize),
+}
+
+impl Shard {
+    /// In: index - index of the shard starting from 0,
+    /// count - quantity of shards.
+    pub fn new(index: usize, count: usize) -> Result<Self, String> {
+        if count == 0 || index >= count {
+            return Err(format!("invalid shard {index} of {count}"));
+        }
+
+        Ok(Self {
+            index,
+            count,
+            mode: ShardMode::default(),
+        })
+    }
+
+    pub fn mode(mut self, mode: ShardMode) -> Self {
+        self.mode = mode;
+        self
+    }
+}
+
+impl Default for Shard {
+    /// Single shard with all numbers.
+    fn default() -> Self {
+        Self {
+            index: 0,
+            count: 1,
+            mode: ShardMode::default(),
+        }
+    }
+}
+
+impl fmt::Display for Shard {
+    /// Shard in the command line form: i/n with i starting from 1.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.index + 1, self.count)?;
+
+        if let ShardMode::Blocks(size) = self.mode {
+            write!(f, " blocks of {size}")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Shard {
+    type Err = String;
+
+    /// Parses the command line form: i/n with i from 1 to n.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid shard {s}, expected i/n with i from 1 to n");
+
+        let (index, count) = s.split_once('/').ok_or_else(invalid)?;
+        let index: usize = index.trim().parse().map_err(|_| invalid())?;
+        let count: usize = count.trim().parse().map_err(|_| invalid())?;
+
+        if index == 0 {
+            return Err(invalid());
+        }
+        Shard::new(index - 1, count).map_err(|_| invalid())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_str() {
+        assert_eq!("2/8".parse::<Shard>(), Shard::new(1, 8));
+        assert_eq!("1/1".parse::<Shard>(), Ok(Shard::default()));
+        assert!("0/8".parse::<Shard>().is_err());
+        assert!("9/8".parse::<Shard>().is_err());
+        assert!("1-8".parse::<Shard>().is_err());
+    }
+}
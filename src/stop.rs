@@ -0,0 +1,28 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Interval of the checks of the stop token by the blocked threads.
+pub(crate) const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Shared flag of cooperative cancellation of the search.
+/// Clones of the token refer to the same flag.
+#[derive(Debug, Clone, Default)]
+pub struct StopToken {
+    stopped: Arc<AtomicBool>,
+}
+
+impl StopToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Asks the workers to stop as soon as possible.
+    pub fn stop(&self) {
+        self.stopped.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.stopped.load(Ordering::Relaxed)
+    }
+}
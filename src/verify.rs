@@ -0,0 +1,143 @@
+use std::io::{self, BufRead};
+
+use crate::payload::Payload;
+use crate::predicate::Predicate;
+
+/// Line of the checked output which failed the verification.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mismatch {
+    /// number of the line starting from 1
+    pub line: usize,
+    pub text: String,
+    pub reason: String,
+}
+
+/// Checks a "number, hash" line of the text output: the hash should be
+/// the digest of the data of the number and satisfy the predicate.
+pub fn verify_line(payload: &Payload, predicate: &Predicate, line: &str) -> Result<(), String> {
+    let (number, hash) = line
+        .split_once(',')
+        .ok_or_else(|| "expected \"number, hash\"".to_string())?;
+    let number: u128 = number
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid number {}", number.trim()))?;
+    let hash = hash.trim();
+
+    let encoding = payload.format().encoding;
+    if number > encoding.max_number() {
+        return Err(format!("number is above the largest {encoding} number"));
+    }
+
+    let digest = payload.digest(number);
+    let expected = hex::encode(&digest);
+
+    if !expected.eq_ignore_ascii_case(hash) {
+        return Err(format!("hash mismatch, expected {expected}"));
+    }
+    if !predicate.matches(&digest) {
+        return Err(format!("hash doesn't satisfy {predicate}"));
+    }
+
+    Ok(())
+}
+
+/// Checks all lines of the reader, the empty lines are skipped.
+/// Out: quantity of checked lines and the failed lines.
+pub fn verify_lines<R: BufRead>(
+    payload: &Payload,
+    predicate: &Predicate,
+    reader: R,
+) -> io::Result<(usize, Vec<Mismatch>)> {
+    let mut checked = 0;
+    let mut mismatches = Vec::new();
+
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        checked += 1;
+        if let Err(reason) = verify_line(payload, predicate, &line) {
+            mismatches.push(Mismatch {
+                line: index + 1,
+                text: line,
+                reason,
+            });
+        }
+    }
+
+    Ok((checked, mismatches))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encoding::{Encoding, NumberFormat};
+    use crate::hasher::Algorithm;
+    use crate::predicate::Position;
+
+    const LINE: &str = "4163, 95d4362bd3cd4315d0bbe38dfa5d7fb8f0aed5f1a31d98d510907279194e3000";
+
+    #[test]
+    fn test_verify_line() {
+        let zeros = |nulls| Predicate::zeros(nulls, Position::Suffix);
+
+        assert_eq!(
+            verify_line(&Algorithm::Sha256.into(), &zeros(3), LINE),
+            Ok(())
+        );
+        assert_eq!(
+            verify_line(&Algorithm::Sha256.into(), &zeros(4), LINE),
+            Err("hash doesn't satisfy suffix=0000".to_string())
+        );
+        assert!(verify_line(
+            &Algorithm::Sha256.into(),
+            &zeros(3),
+            &LINE.replace("4163", "4164")
+        )
+        .is_err());
+        assert!(verify_line(&Algorithm::Md5.into(), &zeros(3), LINE).is_err());
+        assert!(verify_line(&Algorithm::Sha256.into(), &zeros(3), "4163").is_err());
+        assert!(verify_line(&Algorithm::Sha256.into(), &zeros(3), "x, 00").is_err());
+    }
+
+    #[test]
+    fn test_verify_line_encoding() {
+        let format = NumberFormat::new(Encoding::Le32, 0);
+        let payload = Payload::new(Algorithm::Sha256, &[], format, &[]);
+        let hash = sha256::digest(&4163u32.to_le_bytes()[..]);
+
+        assert_eq!(
+            verify_line(
+                &payload,
+                &Predicate::zeros(0, Position::Suffix),
+                &format!("4163, {hash}")
+            ),
+            Ok(())
+        );
+        assert_eq!(
+            verify_line(
+                &payload,
+                &Predicate::zeros(0, Position::Suffix),
+                &format!("4294967296, {hash}")
+            ),
+            Err("number is above the largest le32 number".to_string())
+        );
+    }
+
+    #[test]
+    fn test_verify_lines() {
+        let input = format!("{LINE}\n\n4164, 000\n{}\n", LINE.to_uppercase());
+        let predicate = Predicate::zeros(3, Position::Suffix);
+
+        let (checked, mismatches) =
+            verify_lines(&Algorithm::Sha256.into(), &predicate, input.as_bytes()).unwrap();
+
+        assert_eq!(checked, 3);
+        assert_eq!(mismatches.len(), 1);
+        assert_eq!(mismatches[0].line, 3);
+        assert_eq!(mismatches[0].text, "4164, 000");
+    }
+}